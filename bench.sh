@@ -13,10 +13,10 @@
 set -e
 
 # Linux
-cargo bench --features parallel --all-targets
+cargo bench --features parallel,nightly --all-targets
 
 # WindOS
-WINEPREFIX=/home/jojolepro/.wine64 WINEARCH=win64 cargo bench --target x86_64-pc-windows-gnu --features parallel --all-targets
+WINEPREFIX=/home/jojolepro/.wine64 WINEARCH=win64 cargo bench --target x86_64-pc-windows-gnu --features parallel,nightly --all-targets
 
 # WASM
 # --features parallel not supported on wasm
@@ -25,7 +25,7 @@ WINEPREFIX=/home/jojolepro/.wine64 WINEARCH=win64 cargo bench --target x86_64-pc
 
 # Android
 #cargo dinghy -d android test --features parallel
-cargo dinghy -d android bench --features parallel
+cargo dinghy -d android bench --features parallel,nightly
 
 # InsultingOS
 #cargo dinghy -d ios test --features parallel
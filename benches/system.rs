@@ -10,6 +10,7 @@ struct A;
 fn smol() -> SystemResult {
     Ok(())
 }
+#[allow(clippy::too_many_arguments)]
 fn big(
     _a: &A,
     _b: &A,
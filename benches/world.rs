@@ -16,6 +16,17 @@ fn world_access(b: &mut Bencher) {
     });
 }
 
+#[bench]
+fn world_get(b: &mut Bencher) {
+    #[derive(Default)]
+    struct A;
+    let mut world = World::default();
+    world.initialize::<A>();
+    b.iter(|| {
+        world.get::<A>().unwrap();
+    });
+}
+
 #[bench]
 fn world_create_init(b: &mut Bencher) {
     #[derive(Default)]
@@ -25,3 +36,15 @@ fn world_create_init(b: &mut Bencher) {
         world.initialize::<A>();
     });
 }
+
+#[bench]
+fn world_access_handle(b: &mut Bencher) {
+    #[derive(Default)]
+    struct A;
+    let mut world = World::default();
+    world.initialize::<A>();
+    let handle = world.handle::<A>().unwrap();
+    b.iter(|| {
+        world.get_mut_via(&handle).unwrap();
+    });
+}
@@ -16,7 +16,9 @@ fn system_function(_a: &A, _b: &B, _c: &mut C, d: &mut Option<D>) -> SystemResul
 fn main() {
     let mut world = World::default();
     // Will automatically create A, B, C, Option<D>::None inside of world.
-    let mut dispatch = DispatcherBuilder::new().add(system_function).build(&mut world);
+    let mut dispatch = DispatcherBuilder::new()
+        .add(system_function)
+        .build(&mut world);
     // Let's assign a value to D.
     *world.get_mut::<Option<D>>().unwrap() = Some(D);
 
@@ -26,4 +28,3 @@ fn main() {
 
     assert!(world.get::<Option<D>>().unwrap().is_some());
 }
-
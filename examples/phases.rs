@@ -0,0 +1,35 @@
+use world_dispatcher::*;
+
+struct Update;
+impl Phase for Update {}
+struct Render;
+impl Phase for Render {}
+
+#[derive(Default)]
+pub struct Position(f32);
+#[derive(Default)]
+pub struct Frame(String);
+
+fn movement(position: &mut Position) -> SystemResult {
+    position.0 += 1.0;
+    Ok(())
+}
+
+fn draw(position: &Position, frame: &mut Frame) -> SystemResult {
+    frame.0 = format!("at {}", position.0);
+    Ok(())
+}
+
+fn main() {
+    let mut world = World::default();
+    // Systems in Update always run before the systems in Render, no matter
+    // the order in which they are added.
+    let mut dispatch = Schedule::<(Update, Render)>::new()
+        .add_to_phase(Render, draw)
+        .add_to_phase(Update, movement)
+        .build(&mut world);
+
+    dispatch.run_seq(&world).unwrap();
+
+    assert_eq!(world.get::<Frame>().unwrap().0, "at 1");
+}
@@ -0,0 +1,74 @@
+use crate::*;
+
+/// The number of reads and writes of a resource, along with its type name.
+#[derive(Default)]
+pub(crate) struct AccessCount {
+    name: &'static str,
+    reads: u64,
+    writes: u64,
+}
+
+impl World {
+    /// Counts a call to `get` or `get_mut` for the resource `T`.
+    pub(crate) fn count_access<T: 'static>(&self, write: bool) {
+        let mut stats = self.access_stats.lock().unwrap();
+        let count = stats.entry(TypeId::of::<T>()).or_default();
+        count.name = World::type_name::<T>();
+        if write {
+            count.writes += 1;
+        } else {
+            count.reads += 1;
+        }
+    }
+
+    /// Returns the number of `(reads, writes)` of each resource, indexed by
+    /// type name, since the last call to `reset_access_stats`.
+    ///
+    /// Reads are calls to `get` and writes are calls to `get_mut`,
+    /// which includes the borrows made by running systems. Building or dry
+    /// running a dispatcher doesn't count.
+    /// Only available with the `access_stats` feature.
+    pub fn access_stats(&self) -> HashMap<&'static str, (u64, u64)> {
+        self.access_stats
+            .lock()
+            .unwrap()
+            .values()
+            .map(|c| (c.name, (c.reads, c.writes)))
+            .collect()
+    }
+
+    /// Resets all the access statistics to zero.
+    /// Only available with the `access_stats` feature.
+    pub fn reset_access_stats(&self) {
+        self.access_stats.lock().unwrap().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn count_accesses() {
+        #[derive(Default)]
+        struct A;
+        #[derive(Default)]
+        struct B;
+        let mut world = World::default();
+        let mut dispatch = DispatcherBuilder::new()
+            .add(|_a: &A, _b: &mut B| Ok(()))
+            .add(|_a: &A| Ok(()))
+            .build(&mut world);
+        world.reset_access_stats();
+        dispatch.run_seq(&world).unwrap();
+        dispatch.run_seq(&world).unwrap();
+        let _ = world.get::<u32>();
+        let stats = world.access_stats();
+        assert_eq!(stats[std::any::type_name::<A>()], (4, 0));
+        assert_eq!(stats[std::any::type_name::<B>()], (0, 2));
+        assert_eq!(stats["u32"], (1, 0));
+
+        world.reset_access_stats();
+        assert!(world.access_stats().is_empty());
+    }
+}
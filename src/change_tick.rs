@@ -0,0 +1,252 @@
+use crate::*;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// The change detection of a type registered with
+/// `World::register_change_detect`.
+pub(crate) struct ChangeDetect {
+    clone: CloneFn,
+    eq: fn(&dyn Resource, &dyn Resource) -> bool,
+    /// A copy of the resource from when it was last borrowed mutably, until
+    /// it is compared with the current value.
+    before: Mutex<Option<Box<dyn Resource>>>,
+}
+
+/// Compares a resource registered with `World::register_change_detect` with
+/// its copy when dropped, which `ResMut` does right after releasing its
+/// borrow.
+pub(crate) struct DetectChange<'a> {
+    world: &'a World,
+    typeid: TypeId,
+}
+
+impl Drop for DetectChange<'_> {
+    fn drop(&mut self) {
+        self.world.detect_change(&self.typeid);
+    }
+}
+
+fn eq_resource<T: PartialEq + Send + Sync + 'static>(a: &dyn Resource, b: &dyn Resource) -> bool {
+    a.downcast_ref::<T>() == b.downcast_ref::<T>()
+}
+
+impl World {
+    /// Returns a new change tick, greater than all the previous ones.
+    fn next_tick(&self) -> u64 {
+        self.tick.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    /// Starts tracking the changes of a newly inserted resource.
+    pub(crate) fn insert_tick(&mut self, typeid: TypeId) {
+        if !self.ticks.contains_key(&typeid) {
+            let tick = self.next_tick();
+            self.ticks.insert(typeid, AtomicU64::new(tick));
+        }
+    }
+
+    /// Marks a resource as changed, by its type id.
+    pub(crate) fn bump_tick(&self, typeid: &TypeId) {
+        if let Some(tick) = self.ticks.get(typeid) {
+            tick.store(self.next_tick(), Ordering::Relaxed);
+        }
+    }
+
+    /// Marks a resource as changed when it is borrowed mutably, by its type id.
+    ///
+    /// For the types registered with `register_change_detect`, this copies
+    /// the resource instead, and makes `guard` compare it with the copy once
+    /// released.
+    pub(crate) fn bump_tick_mut<'a>(
+        &'a self,
+        typeid: &TypeId,
+        mut guard: ResMut<'a, Box<dyn Resource>>,
+    ) -> ResMut<'a, Box<dyn Resource>> {
+        if self.change_detect.is_empty() {
+            // Skips looking the type up in the common case.
+            self.bump_tick(typeid);
+            return guard;
+        }
+        match self.change_detect.get(typeid) {
+            Some(detect) => {
+                let mut before = detect.before.lock().unwrap();
+                // Still there if the previous guard was released while another
+                // thread already borrowed the resource mutably again.
+                if let Some(before) = before.take() {
+                    if !(detect.eq)(&*before, &**guard) {
+                        self.bump_tick(typeid);
+                    }
+                }
+                *before = Some((detect.clone)(&**guard));
+                ResMut::detect_change(
+                    &mut guard,
+                    DetectChange {
+                        world: self,
+                        typeid: *typeid,
+                    },
+                );
+            }
+            None => self.bump_tick(typeid),
+        }
+        guard
+    }
+
+    /// Marks a resource registered with `register_change_detect` as changed
+    /// if it differs from the copy made when it was last borrowed mutably.
+    /// Does nothing while it is borrowed mutably, in which case the next
+    /// `bump_tick_mut` compares it.
+    fn detect_change(&self, typeid: &TypeId) {
+        let detect = match self.change_detect.get(typeid) {
+            Some(detect) => detect,
+            None => return,
+        };
+        if let Some(value) = self.res.get(typeid).and_then(|i| i.try_borrow().ok()) {
+            if let Some(before) = detect.before.lock().unwrap().take() {
+                if !(detect.eq)(&*before, &**value) {
+                    self.bump_tick(typeid);
+                }
+            }
+        }
+    }
+
+    /// Drops the copy of a resource made for change detection, when it is
+    /// removed from the world or replaced.
+    pub(crate) fn forget_change(&self, typeid: &TypeId) {
+        if let Some(detect) = self.change_detect.get(typeid) {
+            *detect.before.lock().unwrap() = None;
+        }
+    }
+
+    /// Only marks the resource `T` as changed by a mutable borrow if its
+    /// value actually changed, instead of on every mutable borrow.
+    ///
+    /// Every mutable borrow then clones the value, which is compared with the
+    /// current one when the guard is dropped, so the resource is marked as
+    /// changed at that point. The resource doesn't need to be in the world
+    /// yet.
+    pub fn register_change_detect<T: PartialEq + Clone + Send + Sync + 'static>(&mut self) {
+        self.change_detect.insert(
+            TypeId::of::<T>(),
+            ChangeDetect {
+                clone: clone_resource::<T>,
+                eq: eq_resource::<T>,
+                before: Mutex::new(None),
+            },
+        );
+    }
+
+    /// Marks the resource `T` as changed, without borrowing it.
+    ///
+    /// Resources are already marked as changed when inserted and whenever they
+    /// are borrowed mutably, for example with `get_mut` or by a system taking
+    /// `&mut T`, even if they are not actually modified, unless registered
+    /// with `register_change_detect`. This is only needed
+    /// for resources modified in other ways, such as through interior
+    /// mutability.
+    /// Does nothing if the resource is not in the world.
+    pub fn mark_changed<T: 'static>(&self) {
+        self.bump_tick(&TypeId::of::<T>());
+    }
+
+    /// Returns the change tick of the resource `T`, or `None` if it is not
+    /// in the world.
+    ///
+    /// The tick increases every time the resource is marked as changed, and
+    /// is greater than the ticks of all the resources changed before it. See
+    /// `mark_changed` and `register_change_detect`.
+    pub fn get_change_tick<T: 'static>(&self) -> Option<u64> {
+        self.ticks
+            .get(&TypeId::of::<T>())
+            .map(|tick| tick.load(Ordering::Relaxed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::*;
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn change_ticks() {
+        let mut world = World::default();
+        assert_eq!(world.get_change_tick::<u32>(), None);
+        world.initialize::<u32>();
+        world.initialize::<u64>();
+        let inserted = world.get_change_tick::<u32>().unwrap();
+        assert!(world.get_change_tick::<u64>().unwrap() > inserted);
+
+        let _ = world.get::<u32>().unwrap();
+        world.initialize::<u32>();
+        assert_eq!(world.get_change_tick::<u32>(), Some(inserted));
+
+        let _ = world.get_mut::<u32>().unwrap();
+        let borrowed = world.get_change_tick::<u32>().unwrap();
+        assert!(borrowed > world.get_change_tick::<u64>().unwrap());
+
+        world.mark_changed::<u32>();
+        assert!(world.get_change_tick::<u32>().unwrap() > borrowed);
+        world.mark_changed::<i8>();
+        assert_eq!(world.get_change_tick::<i8>(), None);
+
+        world.take_boxed(TypeId::of::<u32>()).unwrap();
+        assert_eq!(world.get_change_tick::<u32>(), None);
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn change_detect() {
+        let mut world = World::default();
+        world.register_change_detect::<u32>();
+        world.initialize::<u32>();
+        let inserted = world.get_change_tick::<u32>().unwrap();
+
+        let _ = *world.get_mut::<u32>().unwrap();
+        assert_eq!(world.get_change_tick::<u32>(), Some(inserted));
+
+        {
+            let mut value = world.get_mut::<u32>().unwrap();
+            *value = 1;
+            assert_eq!(world.get_change_tick::<u32>(), Some(inserted));
+        }
+        let changed = world.get_change_tick::<u32>().unwrap();
+        assert!(changed > inserted);
+
+        *world.get_mut::<u32>().unwrap() = 1;
+        *world.get_mut::<u32>().unwrap() = 2;
+        let _ = *world.get_mut::<u32>().unwrap();
+        let changed_again = world.get_change_tick::<u32>().unwrap();
+        assert!(changed_again > changed);
+        assert_eq!(world.get_change_tick::<u32>(), Some(changed_again));
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn change_detect_on_release() {
+        let mut world = World::default();
+        world.register_change_detect::<u32>();
+        world.initialize::<u32>();
+        world.initialize::<u64>();
+        *world.get_mut::<u32>().unwrap() = 1;
+        world.mark_changed::<u64>();
+        assert!(world.get_change_tick::<u32>() < world.get_change_tick::<u64>());
+
+        *ResMut::map(world.get_by_typeid(&TypeId::of::<u32>()).unwrap(), |i| {
+            i.downcast_mut::<u32>().unwrap()
+        }) = 2;
+        assert!(world.get_change_tick::<u32>() > world.get_change_tick::<u64>());
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn change_detect_replaced() {
+        let mut world = World::default();
+        world.register_change_detect::<u32>();
+        world.initialize::<u32>();
+        std::mem::forget(world.get_mut::<u32>().unwrap());
+        world.clear_borrows();
+        world = world.with_resource(5u32);
+        let replaced = world.get_change_tick::<u32>().unwrap();
+        let _ = *world.get_mut::<u32>().unwrap();
+        assert_eq!(world.get_change_tick::<u32>(), Some(replaced));
+    }
+}
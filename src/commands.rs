@@ -0,0 +1,36 @@
+use crate::*;
+
+/// A deferred change to the `World`.
+type Command = Box<dyn FnOnce(&mut World) + Send>;
+
+/// A queue of deferred structural changes to the `World`.
+///
+/// Systems only have shared access to the `World` while they run, so they
+/// cannot, for example, initialize new resources. Instead, they can take
+/// `&Commands` as an argument and queue those changes, which
+/// `Dispatcher::run` applies at the end of the current stage, or at the next
+/// flush point, see `DispatcherBuilder::add_flush_point`.
+#[derive(Default)]
+pub struct Commands {
+    queue: Mutex<Vec<Command>>,
+}
+
+impl Commands {
+    /// Queues a change to apply to the `World`.
+    pub fn push<F: FnOnce(&mut World) + Send + 'static>(&self, command: F) {
+        self.queue.lock().unwrap().push(Box::new(command));
+    }
+
+    /// Applies the changes queued in the `World`'s `Commands`, in order.
+    ///
+    /// This is called automatically by `Dispatcher::run`.
+    pub fn apply(world: &mut World) {
+        let queue = world
+            .get_mut::<Commands>()
+            .map(|mut c| std::mem::take(c.queue.get_mut().unwrap()))
+            .unwrap_or_default();
+        for command in queue {
+            command(world);
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use crate::*;
+use std::cell::Cell;
+use std::panic::Location;
+
+thread_local! {
+    static CURRENT_SYSTEM: Cell<Option<&'static str>> = const { Cell::new(None) };
+}
+
+/// Where a resource that is still borrowed was borrowed from.
+/// Only available with the `debug_borrows` feature.
+#[derive(Debug, Clone, Copy)]
+pub struct Borrower {
+    /// The name of the `System` that was running when the resource was
+    /// borrowed, if any.
+    pub system: Option<&'static str>,
+    /// The location of the call to `World::get` or `World::get_mut`.
+    pub location: &'static Location<'static>,
+}
+
+impl Borrower {
+    /// Creates a `Borrower` for the caller, using the `System` currently
+    /// running on this thread.
+    pub(crate) fn new(location: &'static Location<'static>) -> Self {
+        Self {
+            system: CURRENT_SYSTEM.with(|c| c.get()),
+            location,
+        }
+    }
+}
+
+/// Marks a `System` as running on the current thread until dropped.
+pub(crate) struct CurrentSystem(Option<&'static str>);
+
+impl CurrentSystem {
+    pub(crate) fn enter(name: &'static str) -> Self {
+        Self(CURRENT_SYSTEM.with(|c| c.replace(Some(name))))
+    }
+}
+
+impl Drop for CurrentSystem {
+    fn drop(&mut self) {
+        CURRENT_SYSTEM.with(|c| c.set(self.0));
+    }
+}
+
+/// The borrowers of a resource whose guards are still alive, kept in its
+/// `ResourceCell`.
+#[derive(Default)]
+pub(crate) struct Borrowers {
+    next: u64,
+    live: Vec<(u64, Borrower)>,
+}
+
+impl Borrowers {
+    /// Returns the oldest borrower still holding the resource.
+    pub(crate) fn current(&self) -> Option<Borrower> {
+        self.live.first().map(|(_, borrower)| *borrower)
+    }
+}
+
+/// Removes a borrower from the `Borrowers` of a resource when its guard is
+/// dropped.
+pub(crate) struct BorrowerEntry<'a> {
+    borrowers: &'a Mutex<Borrowers>,
+    id: u64,
+}
+
+impl Drop for BorrowerEntry<'_> {
+    fn drop(&mut self) {
+        let mut borrowers = self.borrowers.lock().unwrap();
+        borrowers.live.retain(|(id, _)| *id != self.id);
+    }
+}
+
+/// The guards that can record their borrower, `Res` and `ResMut`.
+pub(crate) trait RecordBorrower<'a> {
+    fn with_borrower(self, entry: BorrowerEntry<'a>) -> Self;
+}
+
+impl World {
+    /// Records the caller as a borrower of a resource, until `guard` is
+    /// dropped.
+    pub(crate) fn record_borrower<'a, G: RecordBorrower<'a>>(
+        &'a self,
+        typeid: TypeId,
+        guard: G,
+        location: &'static Location<'static>,
+    ) -> G {
+        let borrowers = &self.res[&typeid].borrowers;
+        let id = {
+            let mut borrowers = borrowers.lock().unwrap();
+            let id = borrowers.next;
+            borrowers.next += 1;
+            borrowers.live.push((id, Borrower::new(location)));
+            id
+        };
+        guard.with_borrower(BorrowerEntry { borrowers, id })
+    }
+}
@@ -4,60 +4,617 @@ use crate::*;
 ///
 /// For more complex workflows, this builder can have new traits implemented for
 /// it, using the public `systems` member.
-#[derive(Default)]
 pub struct DispatcherBuilder {
     #[doc(hidden)]
     /// The current systems in this builder. Hidden from docs to encourage using
     /// the API of the builder, but public to enable extension of the builder.
     pub systems: Vec<System>,
+    /// The exclusive systems, along with the number of systems that were
+    /// added before them.
+    pub(crate) exclusive: Vec<(usize, ExclusiveSystem)>,
+    pub(crate) shutdown: Vec<System>,
+    pub(crate) auto_initialize: bool,
+    auto_parallelize: bool,
+    pub(crate) profiling: bool,
+    pub(crate) hooks: Vec<SystemRunHook>,
+    warn_on_primitive_resources: bool,
+    /// The resources excluded from `ScheduleWarning::WriteNeverRead`.
+    allowed_unread: Vec<TypeId>,
+    pub(crate) run_criteria: Option<RunCriteriaFn>,
+    pub(crate) abort: Option<RunCriteriaFn>,
+    flush_points: Vec<&'static str>,
+    pub(crate) label: Option<&'static str>,
+    /// The errors found by `extend`, reported by `try_build`.
+    merge_errors: Vec<ScheduleError>,
 }
 
+impl Default for DispatcherBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A system running with exclusive access to the `World`, in between
+/// stages.
+type ExclusiveSystem = ExclusiveFn;
+
+/// The systems of each stage, along with the exclusive systems running after
+/// it.
+type Stages = (Vec<Vec<System>>, Vec<Vec<ExclusiveSystem>>);
+
 impl DispatcherBuilder {
     /// Creates a new `DispatcherBuilder`.
     pub fn new() -> Self {
         Self {
             systems: Vec::default(),
+            exclusive: Vec::default(),
+            shutdown: Vec::default(),
+            auto_initialize: true,
+            auto_parallelize: false,
+            profiling: false,
+            hooks: Vec::default(),
+            warn_on_primitive_resources: false,
+            allowed_unread: Vec::default(),
+            run_criteria: None,
+            abort: None,
+            label: None,
+            flush_points: Vec::default(),
+            merge_errors: Vec::default(),
         }
     }
 
     /// Adds a function implementing `IntoSystem` to the system pool.
+    #[allow(clippy::should_implement_trait)]
     pub fn add<R, F: IntoSystem<R>>(mut self, into_system: F) -> Self {
         self.systems.push(into_system.system());
         self
     }
+    /// Adds each function of a tuple implementing `IntoSystem` to the system
+    /// pool, in order.
+    pub fn add_systems<R, T: IntoSystemTuple<R>>(mut self, systems: T) -> Self {
+        self.systems.extend(systems.systems());
+        self
+    }
     /// Adds a `System` to the system pool.
     pub fn add_system(mut self, system: System) -> Self {
         self.systems.push(system);
         self
     }
+    /// Adds a `System` to the system pool which always runs on the thread
+    /// calling the `Dispatcher`, never on a worker thread, for example to use
+    /// thread-affine GUI or graphics libraries.
+    ///
+    /// It is scheduled like the other systems, and still runs in parallel
+    /// with the systems of its stage running on worker threads. Its resources
+    /// must still be `Send + Sync`, like all the resources of the `World`.
+    pub fn add_main_thread_system(self, mut system: System) -> Self {
+        system.main_thread = true;
+        self.add_system(system)
+    }
+    /// Adds a `System` to the system pool under the given label, unless a
+    /// system with the same label was already added.
+    ///
+    /// Returns whether the system was added. This prevents the same system
+    /// from running twice when combining builders which may overlap.
+    pub fn add_system_unique(&mut self, label: &'static str, system: System) -> bool {
+        if self.systems.iter().any(|s| s.label == Some(label)) {
+            return false;
+        }
+        self.systems.push(system.with_label(label));
+        true
+    }
+    /// Adds a `System` to the system pool, which is run again up to
+    /// `retries` more times if it fails. See `System::with_retries`.
+    pub fn add_system_retry(self, retries: usize, system: System) -> Self {
+        self.add_system(system.with_retries(retries))
+    }
+    /// Adds a function with exclusive access to the `World` to the system
+    /// pool.
+    ///
+    /// Exclusive systems end the current stage and run after all the
+    /// systems added before them. They are only executed by `Dispatcher::run`.
+    pub fn add_exclusive<F: FnMut(&mut World) -> SystemResult + SystemBound + 'static>(
+        mut self,
+        exclusive: F,
+    ) -> Self {
+        self.exclusive
+            .push((self.systems.len(), Box::new(exclusive)));
+        self
+    }
+    /// Adds a point where `Dispatcher::run` applies the `Commands` queued by
+    /// the systems added before it. Like exclusive systems, flush points end
+    /// the current stage.
+    ///
+    /// By default, commands are applied at the end of each stage. Once a
+    /// flush point is added, they are only applied at the flush points, so
+    /// structural changes stay deferred in between.
+    pub fn add_flush_point(mut self, label: &'static str) -> Self {
+        self.flush_points.push(label);
+        self.add_exclusive(|world: &mut World| {
+            Commands::apply(world);
+            Ok(())
+        })
+    }
+    /// Adds a `System` to run once when calling `Dispatcher::shutdown`.
+    ///
+    /// Shutdown systems run in insertion order, after all the other systems.
+    pub fn add_shutdown_system(mut self, system: System) -> Self {
+        self.shutdown.push(system);
+        self
+    }
+    /// Appends the systems of another `DispatcherBuilder` after the systems of
+    /// this one, including its exclusive and shutdown systems.
+    ///
+    /// This is useful to build a `Dispatcher` out of builders provided by
+    /// multiple modules. The settings of both builders are merged, and apply
+    /// to all the systems:
+    /// - The hooks of `other` are called after the ones of this builder
+    /// - The run criteria must all return true, and the abort predicates are
+    ///   checked one after the other
+    /// - `profiling`, `warn_on_primitive_resources` and the resources allowed
+    ///   by `allow_write_never_read` are enabled by either builder
+    /// - The label of `other` is used if this builder has none
+    ///
+    /// `try_build` returns a `ScheduleError::Contradiction` if the builders use
+    /// different `auto_initialize` or `auto_parallelize` settings, or
+    /// different labels, and a `ScheduleError::DuplicateLabel` if a label is
+    /// used by systems of both builders, which usually means the same system
+    /// was added twice. Systems of a single builder can still share a label,
+    /// to be enabled and disabled together.
+    pub fn extend(mut self, other: DispatcherBuilder) -> Self {
+        let labels = self
+            .systems
+            .iter()
+            .chain(&self.shutdown)
+            .filter_map(|sys| sys.label)
+            .collect::<Vec<_>>();
+        let mut duplicates = vec![];
+        for label in other
+            .systems
+            .iter()
+            .chain(&other.shutdown)
+            .filter_map(|sys| sys.label)
+        {
+            if labels.contains(&label) && !duplicates.contains(&label) {
+                duplicates.push(label);
+            }
+        }
+        self.merge_errors.extend(other.merge_errors);
+        self.merge_errors
+            .extend(
+                duplicates
+                    .into_iter()
+                    .map(|label| ScheduleError::DuplicateLabel {
+                        label: label.to_string(),
+                    }),
+            );
+        if self.auto_initialize != other.auto_initialize {
+            self.merge_errors.push(ScheduleError::Contradiction {
+                setting: "auto_initialize",
+            });
+        }
+        if self.auto_parallelize != other.auto_parallelize {
+            self.merge_errors.push(ScheduleError::Contradiction {
+                setting: "auto_parallelize",
+            });
+        }
+        match (self.label, other.label) {
+            (Some(a), Some(b)) if a != b => self
+                .merge_errors
+                .push(ScheduleError::Contradiction { setting: "label" }),
+            (None, label) => self.label = label,
+            _ => {}
+        }
+        self.profiling |= other.profiling;
+        self.warn_on_primitive_resources |= other.warn_on_primitive_resources;
+        self.hooks.extend(other.hooks);
+        self.run_criteria = merge_criteria(self.run_criteria, other.run_criteria, true);
+        self.abort = merge_criteria(self.abort, other.abort, false);
+        let offset = self.systems.len();
+        self.systems.extend(other.systems);
+        self.exclusive.extend(
+            other
+                .exclusive
+                .into_iter()
+                .map(|(at, ex)| (at + offset, ex)),
+        );
+        self.shutdown.extend(other.shutdown);
+        self.flush_points.extend(other.flush_points);
+        self.allowed_unread.extend(other.allowed_unread);
+        self
+    }
+    /// Sets whether `build` initializes the resources used by the systems to
+    /// their default value. Enabled by default.
+    ///
+    /// When disabled, every resource must be inserted manually, and systems
+    /// using resources missing from the `World` fail at runtime. Since their
+    /// conflicts can't be known, they never run in parallel with other
+    /// systems.
+    pub fn auto_initialize(mut self, auto_initialize: bool) -> Self {
+        self.auto_initialize = auto_initialize;
+        self
+    }
+    /// Groups the systems in waves based on their `Access`, instead of
+    /// splitting them in stages whenever two systems conflict.
+    ///
+    /// Each system is added to the first wave after every earlier system it
+    /// conflicts with, so systems with write conflicts still run in the order
+    /// they were added, while the others run in parallel as early as
+    /// possible. Waves never cross exclusive systems. The waves replace the
+    /// stages, see `Dispatcher::waves`.
+    ///
+    /// Systems created manually must describe all the resources they borrow in
+    /// their `Access`.
+    pub fn auto_parallelize(mut self) -> Self {
+        self.auto_parallelize = true;
+        self
+    }
+    /// Makes the systems record the duration of each of their runs, see
+    /// `Dispatcher::profiling_report`. Disabled by default, to avoid the
+    /// overhead.
+    pub fn profiling(mut self) -> Self {
+        self.profiling = true;
+        self
+    }
+    /// Makes the systems call `hook` before and after each of their runs,
+    /// with a `SystemRunEvent` describing the run, to plug in custom
+    /// instrumentation such as metrics or profilers. Hooks are called in the
+    /// order they were added, possibly from several threads at once when the
+    /// systems run in parallel. Exclusive systems don't call them.
+    pub fn on_system_run(mut self, hook: impl Fn(SystemRunEvent) + Send + Sync + 'static) -> Self {
+        self.hooks.push(std::sync::Arc::new(hook));
+        self
+    }
+    /// Makes the `Dispatcher` check `criteria` before running its systems:
+    /// when it returns false, the run does nothing, for example to pause the
+    /// whole schedule. Replaces the previous run criteria, if any.
+    ///
+    /// Shutdown systems always run.
+    pub fn with_run_criteria(
+        mut self,
+        criteria: impl Fn(&World) -> bool + SystemBound + 'static,
+    ) -> Self {
+        #[cfg(not(feature = "sync_systems"))]
+        let criteria = Box::new(criteria);
+        #[cfg(feature = "sync_systems")]
+        let criteria = std::sync::Arc::new(criteria);
+        self.run_criteria = Some(criteria);
+        self
+    }
+    /// Labels the `Dispatcher`, to tell it apart from the other ones, see
+    /// `Dispatcher::label`.
+    pub fn with_label(mut self, label: &'static str) -> Self {
+        self.label = Some(label);
+        self
+    }
+    /// Makes the `Dispatcher` check `predicate` before each system, or each
+    /// stage when running in parallel: when it returns true, the run stops
+    /// early and the remaining systems are skipped, which isn't an error.
+    /// Replaces the previous predicate, if any.
+    ///
+    /// This lets a system request the rest of the schedule to be skipped, for
+    /// example by setting an `AbortRequested` resource, which should then be
+    /// reset before the next run. Unlike `SystemControl`, which a system
+    /// returns to control its own runs, the predicate is checked by the
+    /// dispatcher, between systems.
+    pub fn abort_on(mut self, predicate: impl Fn(&World) -> bool + SystemBound + 'static) -> Self {
+        #[cfg(not(feature = "sync_systems"))]
+        let predicate = Box::new(predicate);
+        #[cfg(feature = "sync_systems")]
+        let predicate = std::sync::Arc::new(predicate);
+        self.abort = Some(predicate);
+        self
+    }
+    /// Makes `validate` return a `ScheduleWarning::PrimitiveResource` for each
+    /// system using a primitive type, such as `u32` or `String`, as a
+    /// resource. Since any system can use these types for unrelated purposes,
+    /// they are better wrapped in a newtype. Disabled by default.
+    pub fn warn_on_primitive_resources(mut self) -> Self {
+        self.warn_on_primitive_resources = true;
+        self
+    }
+    /// Returns advisories about the systems, which don't prevent building the
+    /// `Dispatcher` but often point at mistakes, such as
+    /// `ScheduleWarning::WriteNeverRead`.
+    /// `ScheduleWarning::PrimitiveResource` is only returned after
+    /// `warn_on_primitive_resources`.
+    ///
+    /// Systems taking `&World` are assumed to read every resource, and the
+    /// resources used by exclusive systems aren't known, so they aren't
+    /// taken into account.
+    pub fn validate(&self) -> Vec<ScheduleWarning> {
+        let systems = || self.systems.iter().chain(&self.shutdown);
+        let is_read = |id: &TypeId| {
+            systems().any(|sys| {
+                sys.access.reads.contains(id) || sys.access.reads.contains(&TypeId::of::<World>())
+            })
+        };
+        let mut warnings = vec![];
+        if self.warn_on_primitive_resources {
+            warnings.extend(primitive_resource_warnings(systems()));
+        }
+        for sys in systems() {
+            let access = sys.access();
+            for (id, type_name) in access.writes.iter().zip(&access.write_names) {
+                if !self.allowed_unread.contains(id) && !is_read(id) {
+                    warnings.push(ScheduleWarning::WriteNeverRead {
+                        system: sys.name().to_string(),
+                        resource: type_name.to_string(),
+                    });
+                }
+            }
+        }
+        warnings
+    }
+    /// Excludes the resource `T` from `ScheduleWarning::WriteNeverRead`, for
+    /// resources read outside of the dispatcher, for example by the code
+    /// running it.
+    pub fn allow_write_never_read<T: 'static>(mut self) -> Self {
+        self.allowed_unread.push(TypeId::of::<T>());
+        self
+    }
     /// Builds a `Dispatcher` from the accumulated set of `System`.
     /// This preserves the order from the inserted systems.
+    ///
+    /// Resources are initialized in the order the systems were added, then
+    /// the shutdown systems, so when two systems initialize the same resource
+    /// differently, the first one wins. See
+    /// `Dispatcher::initialization_order`.
+    ///
+    /// Panics if the systems can't be scheduled, see `try_build`.
     pub fn build(self, world: &mut World) -> Dispatcher {
-        for sys in self.systems.iter() {
-            (sys.initialize)(world);
+        self.try_build(world)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+    /// Like `build`, but returns a `ScheduleError` if the systems can't be
+    /// scheduled. The `World` is left untouched when a system can never
+    /// borrow its resources because of its own arguments.
+    pub fn try_build(self, world: &mut World) -> Result<Dispatcher, ScheduleError> {
+        if let Some(error) = self.merge_errors.into_iter().next() {
+            return Err(error);
         }
-        let mut stages: Vec<Vec<System>> = vec![];
-        let mut stage: Vec<System> = vec![];
-        let mut locks = vec![];
-        for sys in self.systems {
-            let mut fetch = (sys.lock)(world, &mut locks);
-            if let Err(_) = fetch {
-                stages.push(stage);
-                stage = vec![];
-                locks.clear();
-                fetch = (sys.lock)(world, &mut locks);
-            }
-            if let Err(_) = fetch {
-                panic!(
-                    "System cannot be borrowed at all. This means it 
-                    uses the same resource twice in its signature."
-                );
+        for sys in self.systems.iter().chain(self.shutdown.iter()) {
+            if let Err(EcsError::ConflictingAccess { type_name }) = sys.access.check_conflicts() {
+                return Err(ScheduleError::Conflict {
+                    system: sys.name().to_string(),
+                    resource: type_name.to_string(),
+                });
             }
+        }
+        let mut initialization_order = vec![];
+        if self.auto_initialize {
+            for sys in self.systems.iter().chain(self.shutdown.iter()) {
+                let access = sys.access();
+                let missing = access
+                    .reads
+                    .iter()
+                    .zip(access.read_names.iter())
+                    .chain(access.writes.iter().zip(access.write_names.iter()))
+                    .filter(|(id, _)| !world.res.contains_key(id))
+                    .collect::<Vec<_>>();
+                (sys.initialize)(world);
+                for (id, name) in missing {
+                    if world.res.contains_key(id) && !initialization_order.contains(name) {
+                        initialization_order.push(*name);
+                    }
+                }
+            }
+        }
+        let (mut stages, exclusive) = if self.auto_parallelize {
+            wave_stages(self.systems, self.exclusive)
+        } else {
+            let (systems, exclusive) = (self.systems, self.exclusive);
+            leak_checked(world, "DispatcherBuilder::build", || {
+                lock_stages(systems, exclusive, world)
+            })?
+        };
+        let mut shutdown = self.shutdown;
+        let mut profiles = vec![];
+        if self.profiling {
+            profiles = stages
+                .iter_mut()
+                .flatten()
+                .chain(shutdown.iter_mut())
+                .map(profile_system)
+                .collect();
+        }
+        for sys in stages.iter_mut().flatten().chain(shutdown.iter_mut()) {
+            hook_system(sys, &self.hooks);
+        }
+        Ok(Dispatcher {
+            stages,
+            exclusive,
+            shutdown,
+            initialization_order,
+            resume: 0,
+            step: 0,
+            disabled: vec![],
+            world_id: world.id(),
+            run_criteria: self.run_criteria,
+            abort: self.abort,
+            flush_points: self.flush_points,
+            label: self.label,
+            profiles,
+            #[cfg(feature = "sync_systems")]
+            hooks: self.hooks,
+        })
+    }
+}
+
+/// Combines the run criteria or abort predicates of two builders merged by
+/// `DispatcherBuilder::extend`, requiring `all` of them to return true, or
+/// any of them.
+fn merge_criteria(
+    a: Option<RunCriteriaFn>,
+    b: Option<RunCriteriaFn>,
+    all: bool,
+) -> Option<RunCriteriaFn> {
+    let (a, b) = match (a, b) {
+        (Some(a), Some(b)) => (a, b),
+        (a, b) => return a.or(b),
+    };
+    let merged = move |world: &World| {
+        if all {
+            a(world) && b(world)
+        } else {
+            a(world) || b(world)
+        }
+    };
+    #[cfg(not(feature = "sync_systems"))]
+    let merged = Box::new(merged);
+    #[cfg(feature = "sync_systems")]
+    let merged = std::sync::Arc::new(merged);
+    Some(merged)
+}
+
+/// Returns a warning for each primitive type used as a resource by the
+/// systems, see `DispatcherBuilder::warn_on_primitive_resources`.
+fn primitive_resource_warnings<'a>(
+    systems: impl Iterator<Item = &'a System>,
+) -> Vec<ScheduleWarning> {
+    let primitives = [
+        TypeId::of::<bool>(),
+        TypeId::of::<char>(),
+        TypeId::of::<u8>(),
+        TypeId::of::<u16>(),
+        TypeId::of::<u32>(),
+        TypeId::of::<u64>(),
+        TypeId::of::<u128>(),
+        TypeId::of::<usize>(),
+        TypeId::of::<i8>(),
+        TypeId::of::<i16>(),
+        TypeId::of::<i32>(),
+        TypeId::of::<i64>(),
+        TypeId::of::<i128>(),
+        TypeId::of::<isize>(),
+        TypeId::of::<f32>(),
+        TypeId::of::<f64>(),
+        TypeId::of::<String>(),
+        TypeId::of::<&'static str>(),
+    ];
+    let mut warnings = vec![];
+    for sys in systems {
+        let access = sys.access();
+        for (id, type_name) in access
+            .reads
+            .iter()
+            .zip(access.read_names.iter())
+            .chain(access.writes.iter().zip(access.write_names.iter()))
+        {
+            if primitives.contains(id) {
+                warnings.push(ScheduleWarning::PrimitiveResource {
+                    system: sys.name().to_string(),
+                    resource: type_name.to_string(),
+                });
+            }
+        }
+    }
+    warnings
+}
+
+/// Splits the systems in stages, starting a new stage whenever a system can't
+/// borrow its resources along with the systems of the current stage.
+fn lock_stages(
+    systems: Vec<System>,
+    exclusive: Vec<(usize, ExclusiveSystem)>,
+    world: &World,
+) -> Result<Stages, ScheduleError> {
+    let mut stages: Vec<Vec<System>> = vec![];
+    let mut stage: Vec<System> = vec![];
+    let mut after = vec![];
+    let mut pending = vec![];
+    let mut remaining = exclusive.into_iter().peekable();
+    let mut locks = vec![];
+    for (i, sys) in systems.into_iter().enumerate() {
+        while let Some((_, ex)) = remaining.next_if(|(at, _)| *at == i) {
+            pending.push(ex);
+        }
+        if !pending.is_empty() {
+            stages.push(stage);
+            after.push(pending);
+            stage = vec![];
+            pending = vec![];
+            locks.clear();
+        }
+        let mut fetch = (sys.lock)(world, &mut locks);
+        if fetch.is_err() {
+            stages.push(stage);
+            after.push(vec![]);
+            stage = vec![];
+            locks.clear();
+            fetch = (sys.lock)(world, &mut locks);
+        }
+        if let Err(EcsError::NotInitialized) = fetch {
+            // The system uses missing resources and will fail when running, keep it
+            // alone in its stage.
             stage.push(sys);
+            stages.push(stage);
+            after.push(vec![]);
+            stage = vec![];
+            locks.clear();
+            continue;
+        }
+        match fetch {
+            Err(EcsError::ConflictingAccess { type_name }) => {
+                return Err(ScheduleError::Conflict {
+                    system: sys.name().to_string(),
+                    resource: type_name.to_string(),
+                })
+            }
+            Err(error) => {
+                return Err(ScheduleError::Unborrowable {
+                    system: sys.name().to_string(),
+                    error,
+                })
+            }
+            Ok(()) => stage.push(sys),
+        }
+    }
+    pending.extend(remaining.map(|(_, ex)| ex));
+    stages.push(stage);
+    after.push(pending);
+    Ok((stages, after))
+}
+
+/// Splits the systems in waves using their `Access`: each system is added to
+/// the wave following the last one containing a conflicting system.
+fn wave_stages(systems: Vec<System>, exclusive: Vec<(usize, ExclusiveSystem)>) -> Stages {
+    // Closes the waves preceding exclusive systems.
+    fn close(result: &mut Stages, mut waves: Vec<Vec<System>>, pending: Vec<ExclusiveSystem>) {
+        if waves.is_empty() {
+            waves.push(vec![]);
+        }
+        result.1.extend((1..waves.len()).map(|_| vec![]));
+        result.1.push(pending);
+        result.0.extend(waves);
+    }
+    let mut result = (vec![], vec![]);
+    let mut waves: Vec<Vec<System>> = vec![];
+    let mut pending = vec![];
+    let mut remaining = exclusive.into_iter().peekable();
+    for (i, sys) in systems.into_iter().enumerate() {
+        while let Some((_, ex)) = remaining.next_if(|(at, _)| *at == i) {
+            pending.push(ex);
+        }
+        if !pending.is_empty() {
+            close(
+                &mut result,
+                std::mem::take(&mut waves),
+                std::mem::take(&mut pending),
+            );
         }
-        stages.push(stage);
-        Dispatcher { stages }
+        let wave = waves
+            .iter()
+            .rposition(|w| w.iter().any(|s| s.access.conflicts_with(&sys.access)))
+            .map_or(0, |i| i + 1);
+        if wave == waves.len() {
+            waves.push(vec![]);
+        }
+        waves[wave].push(sys);
     }
+    pending.extend(remaining.map(|(_, ex)| ex));
+    close(&mut result, waves, pending);
+    result
 }
 
 /// A dispatcher is used to execute a collection of `System` in order and
@@ -65,62 +622,715 @@ impl DispatcherBuilder {
 /// A dispatcher automatically avoids mutable borrow collisions which would
 /// normally lead to data corruption, dead locks and more.
 pub struct Dispatcher {
-    stages: Vec<Vec<System>>,
+    pub(crate) stages: Vec<Vec<System>>,
+    /// The exclusive systems to run after each stage.
+    pub(crate) exclusive: Vec<Vec<ExclusiveSystem>>,
+    pub(crate) shutdown: Vec<System>,
+    /// The type names of the resources initialized by `build`, in order.
+    pub(crate) initialization_order: Vec<&'static str>,
+    /// The index of the next system to run with `run_with_budget`, counting
+    /// across stages.
+    pub(crate) resume: usize,
+    /// The index of the next system to run with `step`, counting across
+    /// stages.
+    pub(crate) step: usize,
+    /// The labels of the systems disabled with `set_enabled`.
+    pub(crate) disabled: Vec<&'static str>,
+    /// The id of the `World` the dispatcher was built with.
+    pub(crate) world_id: WorldId,
+    /// Decides whether the systems run, see
+    /// `DispatcherBuilder::with_run_criteria`.
+    pub(crate) run_criteria: Option<RunCriteriaFn>,
+    /// Decides whether to stop a run early, see `DispatcherBuilder::abort_on`.
+    pub(crate) abort: Option<RunCriteriaFn>,
+    /// The labels of the flush points, see
+    /// `DispatcherBuilder::add_flush_point`.
+    pub(crate) flush_points: Vec<&'static str>,
+    /// The label of the dispatcher, see `DispatcherBuilder::with_label`.
+    pub(crate) label: Option<&'static str>,
+    /// The profile of each system, when built with
+    /// `DispatcherBuilder::profiling`.
+    pub(crate) profiles: Vec<SharedProfile>,
+    /// The hooks added with `DispatcherBuilder::on_system_run`, kept for
+    /// `Dispatcher::to_builder`.
+    #[cfg(feature = "sync_systems")]
+    pub(crate) hooks: Vec<SystemRunHook>,
+}
+
+/// How far `Dispatcher::run_with_budget` got.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RunProgress {
+    /// The number of systems run by this call.
+    pub ran: usize,
+    /// The number of systems left to run, which the next call starts with.
+    pub remaining: usize,
+}
+
+impl RunProgress {
+    /// Returns whether all the systems have run.
+    pub fn is_finished(&self) -> bool {
+        self.remaining == 0
+    }
+}
+
+/// Measures elapsed time, using the JavaScript clock on the web where
+/// `std::time::Instant` is not available, with the `wasm` feature.
+pub(crate) struct Stopwatch {
+    #[cfg(not(all(target_arch = "wasm32", feature = "wasm")))]
+    start: std::time::Instant,
+    #[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+    start: f64,
+}
+
+impl Stopwatch {
+    pub(crate) fn start() -> Self {
+        #[cfg(not(all(target_arch = "wasm32", feature = "wasm")))]
+        return Self {
+            start: std::time::Instant::now(),
+        };
+        #[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+        return Self {
+            start: js_sys::Date::now(),
+        };
+    }
+
+    pub(crate) fn elapsed(&self) -> std::time::Duration {
+        #[cfg(not(all(target_arch = "wasm32", feature = "wasm")))]
+        return self.start.elapsed();
+        #[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+        return std::time::Duration::from_secs_f64(
+            (js_sys::Date::now() - self.start).max(0.0) / 1000.0,
+        );
+    }
 }
 impl Dispatcher {
+    /// Asserts, in debug builds, that the dispatcher runs with the `World`
+    /// it was built with, which initialized its resources and decided its
+    /// stages. Use `DispatcherBuilder::build_template` to run the same
+    /// systems on several worlds.
+    fn check_world(&self, world: &World) {
+        debug_assert_eq!(
+            self.world_id,
+            world.id(),
+            "The dispatcher runs with a different `World` than the one it was built with."
+        );
+    }
+
+    /// Returns whether the run criteria allow the systems to run.
+    fn should_run(&self, world: &World) -> bool {
+        self.run_criteria
+            .as_ref()
+            .is_none_or(|criteria| criteria(world))
+    }
+
     /// Returns an iterator of all stages. This is not needed for regular use,
     /// but can be useful for debugging or for implementing custom executors.
     pub fn iter_stages(&self) -> impl Iterator<Item = &Vec<System>> {
         self.stages.iter()
     }
 
-    /// Runs the systems one after the other, one at a time.
-    pub fn run_seq(&mut self, world: &World) -> SystemResult {
+    /// Runs the systems, in parallel if the `parallel` or `std_parallel`
+    /// feature is enabled.
+    ///
+    /// At the end of each stage, the `Commands` queued by the systems are
+    /// applied, unless the dispatcher has flush points, then the exclusive
+    /// systems are run. Both have full access to the `World`.
+    pub fn run(&mut self, world: &mut World) -> SystemResult {
+        self.check_world(world);
+        self.step = 0;
+        if !self.should_run(world) {
+            return Ok(());
+        }
         #[cfg(feature = "profiler")]
-        profile_scope!("dispatcher_run_seq");
+        profile_scope!("dispatcher_run");
 
-        for stage in &mut self.stages {
-            let errors = stage
+        for (stage, exclusive) in self.stages.iter_mut().zip(self.exclusive.iter_mut()) {
+            if is_aborted(&self.abort, world) {
+                break;
+            }
+            #[cfg(feature = "parallel")]
+            run_stage_par(stage, &self.disabled, world)?;
+            #[cfg(all(feature = "std_parallel", not(feature = "parallel")))]
+            run_stage_scoped(stage, &self.disabled, world)?;
+            #[cfg(not(any(feature = "parallel", feature = "std_parallel")))]
+            run_stage_seq(stage, &self.disabled, &self.abort, world, |_| true)?;
+            if self.flush_points.is_empty() {
+                Commands::apply(world);
+            }
+            if is_aborted(&self.abort, world) {
+                break;
+            }
+            let errors = exclusive
                 .iter_mut()
-                .map(|s| s.run(world))
+                .map(|s| s(world))
                 .flat_map(|r| r.err())
                 .collect::<Vec<_>>();
-            if errors.len() > 0 {
+            if !errors.is_empty() {
                 return Err(EcsError::DispatcherExecutionFailed(errors));
             }
         }
         Ok(())
     }
+
+    /// Enables or disables the systems with the given label, see
+    /// `System::with_label`. Returns whether such a system was found.
+    ///
+    /// Disabled systems are skipped by all the `run` methods, but keep their
+    /// place in the schedule, so they run in the same order once enabled
+    /// again. Shutdown systems are not affected.
+    pub fn set_enabled(&mut self, label: &str, enabled: bool) -> bool {
+        let label = match self
+            .stages
+            .iter()
+            .flatten()
+            .find_map(|sys| sys.label.filter(|l| *l == label))
+        {
+            Some(label) => label,
+            None => return false,
+        };
+        self.disabled.retain(|l| *l != label);
+        if !enabled {
+            self.disabled.push(label);
+        }
+        true
+    }
+
+    /// Returns whether the systems with the given label are enabled, see
+    /// `set_enabled`. Returns false if there is no such system.
+    pub fn is_enabled(&self, label: &str) -> bool {
+        self.stages
+            .iter()
+            .flatten()
+            .any(|sys| sys.label == Some(label))
+            && !self.disabled.contains(&label)
+    }
+
+    /// Returns the label of the dispatcher, given by
+    /// `DispatcherBuilder::with_label`.
+    pub fn label(&self) -> Option<&'static str> {
+        self.label
+    }
+
+    /// Returns whether the dispatcher has a system with the given label, see
+    /// `System::with_label`, including its shutdown systems.
+    pub fn contains_system(&self, label: &str) -> bool {
+        self.stages
+            .iter()
+            .flatten()
+            .chain(self.shutdown.iter())
+            .any(|sys| sys.label == Some(label))
+    }
+
+    /// Returns the labels of the flush points, in order, see
+    /// `DispatcherBuilder::add_flush_point`.
+    pub fn flush_points(&self) -> &[&'static str] {
+        &self.flush_points
+    }
+
+    /// Returns the names of the systems of each stage, or wave when using
+    /// `DispatcherBuilder::auto_parallelize`, in execution order.
+    pub fn waves(&self) -> Vec<Vec<&'static str>> {
+        self.stages
+            .iter()
+            .map(|stage| stage.iter().map(|sys| sys.name()).collect())
+            .collect()
+    }
+
+    /// Returns a deterministic, human-readable description of the execution
+    /// order, for example to catch accidental reordering in snapshot tests.
+    ///
+    /// Each stage is written as a `stage <index>` line followed by one
+    /// line per system, indented by two spaces, with the label of the system
+    /// if it has one and its name otherwise. When exclusive systems run after
+    /// a stage, an `exclusive <count>` line follows it. Shutdown systems are
+    /// listed last in the same way, under a `shutdown` line, if any.
+    pub fn schedule_digest(&self) -> String {
+        fn system_line(digest: &mut String, sys: &System) {
+            digest.push_str("  ");
+            digest.push_str(sys.label().unwrap_or_else(|| sys.name()));
+            digest.push('\n');
+        }
+        let mut digest = String::new();
+        for (i, (stage, exclusive)) in self.stages.iter().zip(self.exclusive.iter()).enumerate() {
+            digest.push_str(&format!("stage {}\n", i));
+            for sys in stage {
+                system_line(&mut digest, sys);
+            }
+            if !exclusive.is_empty() {
+                digest.push_str(&format!("exclusive {}\n", exclusive.len()));
+            }
+        }
+        if !self.shutdown.is_empty() {
+            digest.push_str("shutdown\n");
+            for sys in &self.shutdown {
+                system_line(&mut digest, sys);
+            }
+        }
+        digest
+    }
+
+    /// Returns the type names of the resources initialized when building this
+    /// dispatcher, in the order they were initialized.
+    ///
+    /// Resources are listed as declared by the `Access` of the system
+    /// initializing them. Resources which were already in the `World` aren't
+    /// listed.
+    pub fn initialization_order(&self) -> Vec<&'static str> {
+        self.initialization_order.clone()
+    }
+
+    /// Checks that every system can borrow all of its resources from the
+    /// provided `World`, without running any of them. The resources aren't
+    /// marked as changed, and the borrows aren't counted by `access_stats`.
+    ///
+    /// Returns the name of each system that can't, along with the error.
+    /// Exclusive systems aren't checked.
+    pub fn dry_run(&self, world: &World) -> Result<(), Vec<(String, EcsError)>> {
+        let errors = self
+            .stages
+            .iter()
+            .flatten()
+            .chain(self.shutdown.iter())
+            .filter_map(|sys| {
+                leak_checked(world, sys.name, || {
+                    let mut locks = vec![];
+                    (sys.lock)(world, &mut locks)
+                })
+                .err()
+                .map(|e| (sys.name.to_string(), e))
+            })
+            .collect::<Vec<_>>();
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        Ok(())
+    }
+
+    /// Runs the shutdown systems one after the other.
+    ///
+    /// Shutdown systems only run once: calling this again does nothing.
+    pub fn shutdown(&mut self, world: &World) -> SystemResult {
+        self.check_world(world);
+        let errors = self
+            .shutdown
+            .drain(..)
+            .map(|mut s| s.run(world))
+            .flat_map(|r| r.err())
+            .collect::<Vec<_>>();
+        if !errors.is_empty() {
+            return Err(EcsError::DispatcherExecutionFailed(errors));
+        }
+        Ok(())
+    }
+
+    /// Runs the systems, in parallel if the `parallel` or `std_parallel`
+    /// feature is enabled, without making structural changes to the `World`.
+    ///
+    /// Exclusive systems are not run and `Commands` are not applied, so this is
+    /// only suitable for dispatchers not using them.
+    pub fn run_ref(&mut self, world: &World) -> SystemResult {
+        #[cfg(feature = "parallel")]
+        return self.run_par(world);
+        #[cfg(all(feature = "std_parallel", not(feature = "parallel")))]
+        return self.par_run_with_scope(world);
+        #[cfg(not(any(feature = "parallel", feature = "std_parallel")))]
+        return self.run_seq(world);
+    }
+
+    /// Runs the systems like `run_ref` until `done` returns true, checking it
+    /// after each run.
+    ///
+    /// Returns the number of runs, or `EcsError::MaxIterationsExceeded` if
+    /// `done` still returns false after `max_iters` runs. Errors returned by
+    /// the systems stop the loop and are returned as is.
+    pub fn run_until(
+        &mut self,
+        world: &World,
+        max_iters: usize,
+        done: impl Fn(&World) -> bool,
+    ) -> Result<usize, EcsError> {
+        for i in 1..=max_iters {
+            self.run_ref(world)?;
+            if done(world) {
+                return Ok(i);
+            }
+        }
+        Err(EcsError::MaxIterationsExceeded(max_iters))
+    }
+
+    /// Runs the systems one after the other until `budget` is exceeded, then
+    /// returns how far it got. The next call resumes with the following
+    /// system, and starts over once all the systems have run.
+    ///
+    /// The elapsed time is checked between systems, so at least one system runs
+    /// per call and the budget can be exceeded by the duration of the last
+    /// one. Systems disabled with `set_enabled` are skipped without counting
+    /// as run. The systems which failed are reported once the call returns,
+    /// along with the progress, and the position is kept. Like `run_ref`, this
+    /// doesn't run exclusive systems nor apply `Commands`.
+    pub fn run_with_budget(
+        &mut self,
+        world: &World,
+        budget: std::time::Duration,
+    ) -> Result<RunProgress, (RunProgress, EcsError)> {
+        self.check_world(world);
+        let stopwatch = Stopwatch::start();
+        let total = self.stages.iter().map(Vec::len).sum::<usize>();
+        if !self.should_run(world) {
+            return Ok(RunProgress {
+                ran: 0,
+                remaining: total - self.resume,
+            });
+        }
+        let mut errors = vec![];
+        let mut ran = 0;
+        let mut aborted = false;
+        let mut passed = 0;
+        for sys in self.stages.iter_mut().flatten().skip(self.resume) {
+            // Disabled systems are passed without counting as run, so that
+            // they don't use up the budget.
+            if !is_enabled(&self.disabled, sys) {
+                passed += 1;
+                continue;
+            }
+            if ran > 0 && stopwatch.elapsed() >= budget {
+                break;
+            }
+            if is_aborted(&self.abort, world) {
+                aborted = true;
+                break;
+            }
+            ran += 1;
+            passed += 1;
+            if let Err(e) = sys.run(world) {
+                errors.push(e);
+            }
+        }
+        self.resume += passed;
+        let remaining = if aborted { 0 } else { total - self.resume };
+        if remaining == 0 {
+            self.resume = 0;
+        }
+        let progress = RunProgress { ran, remaining };
+        if !errors.is_empty() {
+            return Err((progress, EcsError::DispatcherExecutionFailed(errors)));
+        }
+        Ok(progress)
+    }
+
+    /// Runs the next system, in the order of `run_seq`, and returns its name
+    /// along with its result. Returns `None` once all the systems have run,
+    /// until the position is reset by running all the systems with `run`,
+    /// `run_ref` or `run_seq`.
+    ///
+    /// Useful to debug a schedule. Systems disabled with `set_enabled` are
+    /// skipped, while the run criteria aren't checked. Like `run_ref`, this
+    /// doesn't run exclusive systems nor apply `Commands`.
+    pub fn step(&mut self, world: &World) -> Option<(&'static str, SystemResult)> {
+        self.check_world(world);
+        let disabled = &self.disabled;
+        let (skipped, sys) = self
+            .stages
+            .iter_mut()
+            .flatten()
+            .skip(self.step)
+            .enumerate()
+            .find(|(_, sys)| is_enabled(disabled, sys))?;
+        self.step += skipped + 1;
+        Some((sys.name(), sys.run(world)))
+    }
+
+    /// Runs the systems one after the other, one at a time.
+    ///
+    /// Like `run_ref`, this doesn't run exclusive systems nor apply `Commands`.
+    pub fn run_seq(&mut self, world: &World) -> SystemResult {
+        self.check_world(world);
+        self.step = 0;
+        if !self.should_run(world) {
+            return Ok(());
+        }
+        #[cfg(feature = "profiler")]
+        profile_scope!("dispatcher_run_seq");
+
+        for stage in &mut self.stages {
+            if is_aborted(&self.abort, world) {
+                break;
+            }
+            run_stage_seq(stage, &self.disabled, &self.abort, world, |_| true)?;
+        }
+        Ok(())
+    }
+    /// Like `run_seq`, but only runs the systems whose name matches
+    /// `predicate`, for example to debug the systems of a single module by
+    /// the prefix of their name.
+    pub fn run_filtered(
+        &mut self,
+        world: &World,
+        predicate: impl Fn(&'static str) -> bool,
+    ) -> SystemResult {
+        self.check_world(world);
+        self.step = 0;
+        if !self.should_run(world) {
+            return Ok(());
+        }
+        for stage in &mut self.stages {
+            if is_aborted(&self.abort, world) {
+                break;
+            }
+            run_stage_seq(stage, &self.disabled, &self.abort, world, |s| {
+                predicate(s.name())
+            })?;
+        }
+        Ok(())
+    }
     /// Runs the systems in parallel. Systems having conflicts in their
     /// dependencies (the resource reference they use are the same and at least
     /// one is mutable) are run sequentially relative to each other, while
     /// systems without conflict run in parallel.
+    ///
+    /// Like `run_ref`, this doesn't run exclusive systems nor apply `Commands`.
     #[cfg(feature = "parallel")]
     pub fn run_par(&mut self, world: &World) -> SystemResult {
+        self.check_world(world);
+        self.step = 0;
+        if !self.should_run(world) {
+            return Ok(());
+        }
         #[cfg(feature = "profiler")]
         profile_scope!("dispatcher_run_par");
 
         for stage in &mut self.stages {
-            let errors = stage
-                .par_iter_mut()
-                .map(|s| s.run(world))
-                .flat_map(|r| r.err())
-                .collect::<Vec<_>>();
-            if errors.len() > 0 {
-                return Err(EcsError::DispatcherExecutionFailed(errors));
+            if is_aborted(&self.abort, world) {
+                break;
             }
+            run_stage_par(stage, &self.disabled, world)?;
         }
         Ok(())
     }
+    /// Runs the systems in parallel like `run_par`, using scoped threads
+    /// from the standard library instead of `rayon`.
+    ///
+    /// The systems of a stage run on their own threads, except for the
+    /// main-thread systems, or the last system of the stage if there are
+    /// none, which run on the calling thread. The stage ends once all of them
+    /// are done. Errors are reported in the order the systems were added,
+    /// regardless of which thread finished first.
+    ///
+    /// Like `run_ref`, this doesn't run exclusive systems nor apply `Commands`.
+    #[cfg(feature = "std_parallel")]
+    pub fn par_run_with_scope(&mut self, world: &World) -> SystemResult {
+        self.check_world(world);
+        self.step = 0;
+        if !self.should_run(world) {
+            return Ok(());
+        }
+        #[cfg(feature = "profiler")]
+        profile_scope!("dispatcher_par_run_with_scope");
+
+        for stage in &mut self.stages {
+            if is_aborted(&self.abort, world) {
+                break;
+            }
+            run_stage_scoped(stage, &self.disabled, world)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for Dispatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Dispatcher")
+            .field("label", &self.label)
+            .field("stages", &self.waves())
+            .field(
+                "shutdown",
+                &self.shutdown.iter().map(|s| s.name()).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+/// Returns whether a system is not disabled, see `Dispatcher::set_enabled`.
+fn is_enabled(disabled: &[&'static str], sys: &System) -> bool {
+    sys.label.is_none_or(|label| !disabled.contains(&label))
+}
+
+/// Runs `f`, panicking in debug builds if it leaves more guards extended by
+/// `System::lock` alive than before, as they should all be dropped once it
+/// returns. Wraps the places holding these guards, `lock_stages` and
+/// `Dispatcher::dry_run`.
+fn leak_checked<R>(world: &World, name: &str, f: impl FnOnce() -> R) -> R {
+    #[cfg(debug_assertions)]
+    let borrowed = world.outstanding_borrows();
+    let result = f();
+    #[cfg(debug_assertions)]
+    assert!(
+        world.outstanding_borrows() <= borrowed,
+        "`{}` leaked a borrow of a resource of the `World`.",
+        name
+    );
+    #[cfg(not(debug_assertions))]
+    let _ = (world, name);
+    result
+}
+
+/// Returns whether the run should stop, see `DispatcherBuilder::abort_on`.
+fn is_aborted(abort: &Option<RunCriteriaFn>, world: &World) -> bool {
+    abort.as_ref().is_some_and(|abort| abort(world))
+}
+
+/// Runs the enabled systems of a stage matching `filter` one after the other,
+/// until the run is aborted.
+fn run_stage_seq(
+    stage: &mut [System],
+    disabled: &[&'static str],
+    abort: &Option<RunCriteriaFn>,
+    world: &World,
+    filter: impl Fn(&System) -> bool,
+) -> SystemResult {
+    let errors = stage
+        .iter_mut()
+        .filter(|s| is_enabled(disabled, s) && filter(s))
+        .take_while(|_| !is_aborted(abort, world))
+        .map(|s| s.run(world))
+        .flat_map(|r| r.err())
+        .collect::<Vec<_>>();
+    if !errors.is_empty() {
+        return Err(EcsError::DispatcherExecutionFailed(errors));
+    }
+    Ok(())
+}
+
+/// Runs the enabled systems of a stage in parallel.
+///
+/// Main-thread systems run on the current thread while the others run on the
+/// thread pool.
+#[cfg(feature = "parallel")]
+fn run_stage_par(stage: &mut [System], disabled: &[&'static str], world: &World) -> SystemResult {
+    let (main, others): (Vec<_>, Vec<_>) = stage
+        .iter_mut()
+        .filter(|s| is_enabled(disabled, s))
+        .partition(|s| s.main_thread);
+    let mut errors = vec![];
+    let mut main_errors = vec![];
+    rayon::in_place_scope(|scope| {
+        let errors = &mut errors;
+        scope.spawn(move |_| {
+            *errors = others
+                .into_par_iter()
+                .map(|s| s.run(world))
+                .flat_map(|r| r.err())
+                .collect::<Vec<_>>();
+        });
+        main_errors = main
+            .into_iter()
+            .map(|s| s.run(world))
+            .flat_map(|r| r.err())
+            .collect::<Vec<_>>();
+    });
+    errors.extend(main_errors);
+    if !errors.is_empty() {
+        return Err(EcsError::DispatcherExecutionFailed(errors));
+    }
+    Ok(())
+}
+
+/// Calls `f` on each of the items, in parallel using `rayon`, from inside a
+/// system. Only available with the `parallel` feature.
+///
+/// `f` only gets a shared `&World`, so resources must not be borrowed
+/// mutably by the calling system nor by `f`. Writes must go through
+/// thread-safe resources, such as a `Mutex`, or be deferred with `Commands`.
+/// The errors returned by `f` are collected into an
+/// `EcsError::DispatcherExecutionFailed`.
+/// ```
+/// use world_dispatcher::*;
+/// use std::sync::atomic::{AtomicU32, Ordering};
+///
+/// #[derive(Default)]
+/// struct Total(AtomicU32);
+///
+/// fn sum(world: &World) -> SystemResult {
+///     parallel_for(world, 1..=10u32, |world, n| {
+///         world.get::<Total>()?.0.fetch_add(n, Ordering::Relaxed);
+///         Ok(())
+///     })
+/// }
+///
+/// let mut world = World::default();
+/// world.initialize::<Total>();
+/// sum.system().run(&world).unwrap();
+/// assert_eq!(world.get::<Total>().unwrap().0.load(Ordering::Relaxed), 55);
+/// ```
+#[cfg(feature = "parallel")]
+pub fn parallel_for<T: Send, I: IntoParallelIterator<Item = T>>(
+    world: &World,
+    items: I,
+    f: impl Fn(&World, T) -> SystemResult + Send + Sync,
+) -> SystemResult {
+    let errors = items
+        .into_par_iter()
+        .map(|item| f(world, item))
+        .flat_map(|r| r.err())
+        .collect::<Vec<_>>();
+    if !errors.is_empty() {
+        return Err(EcsError::DispatcherExecutionFailed(errors));
+    }
+    Ok(())
+}
+
+/// Runs the enabled systems of a stage in parallel, using scoped threads.
+///
+/// The main-thread systems run on the current thread, or the last system if
+/// there are none.
+#[cfg(feature = "std_parallel")]
+fn run_stage_scoped(
+    stage: &mut [System],
+    disabled: &[&'static str],
+    world: &World,
+) -> SystemResult {
+    let (mut main, mut others): (Vec<_>, Vec<_>) = stage
+        .iter_mut()
+        .filter(|s| is_enabled(disabled, s))
+        .enumerate()
+        .partition(|(_, s)| s.main_thread);
+    if main.is_empty() {
+        main.extend(others.pop());
+    }
+    let errors = std::thread::scope(|scope| {
+        let handles = others
+            .into_iter()
+            .map(|(i, s)| (i, scope.spawn(move || s.run(world))))
+            .collect::<Vec<_>>();
+        let mut results = main
+            .into_iter()
+            .map(|(i, s)| (i, s.run(world)))
+            .collect::<Vec<_>>();
+        results.extend(
+            handles
+                .into_iter()
+                .map(|(i, h)| (i, h.join().unwrap_or_else(|e| std::panic::resume_unwind(e)))),
+        );
+        results.sort_by_key(|(i, _)| *i);
+        results
+            .into_iter()
+            .flat_map(|(_, r)| r.err())
+            .collect::<Vec<_>>()
+    });
+    if !errors.is_empty() {
+        return Err(EcsError::DispatcherExecutionFailed(errors));
+    }
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use crate::*;
+    #[cfg(target_arch = "wasm32")]
     use wasm_bindgen_test::*;
 
     #[test]
-    #[wasm_bindgen_test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
     fn simple_dispatcher() {
         #[derive(Default)]
         pub struct A;
@@ -135,7 +1345,7 @@ mod tests {
     }
 
     #[test]
-    #[wasm_bindgen_test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
     fn generic_simple_dispatcher() {
         #[derive(Default)]
         pub struct A;
@@ -156,7 +1366,7 @@ mod tests {
 
     #[cfg(feature = "parallel")]
     #[test]
-    #[wasm_bindgen_test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
     fn par_distpach() {
         #[derive(Default)]
         pub struct A;
@@ -170,9 +1380,78 @@ mod tests {
         assert!(world.get_mut::<A>().is_ok());
     }
 
+    #[cfg(feature = "std_parallel")]
+    #[test]
+    fn scoped_dispatch() {
+        #[derive(Default)]
+        struct A(u32);
+        #[derive(Default)]
+        struct B(u32);
+        #[derive(Default)]
+        struct C(Vec<u32>);
+        fn sys1(a: &A, b: &mut B) -> SystemResult {
+            b.0 += a.0;
+            Ok(())
+        }
+        fn sys2(a: &A, c: &mut C) -> SystemResult {
+            c.0.push(a.0);
+            Ok(())
+        }
+        fn sys3(b: &B, c: &mut C) -> SystemResult {
+            c.0.push(b.0);
+            Ok(())
+        }
+        fn failing(_a: &A) -> SystemResult {
+            Err(EcsError::NotInitialized)
+        }
+        let mut world = World::default();
+        let mut dispatch = DispatcherBuilder::new()
+            .add(sys1)
+            .add(sys2)
+            .add(sys3)
+            .build(&mut world);
+        world.get_mut::<A>().unwrap().0 = 2;
+        for _ in 0..10 {
+            dispatch.par_run_with_scope(&world).unwrap();
+        }
+        assert_eq!(world.get::<B>().unwrap().0, 20);
+        let expected = (1..=10).flat_map(|i| vec![2, i * 2]).collect::<Vec<_>>();
+        assert_eq!(world.get::<C>().unwrap().0, expected);
+
+        let mut dispatch = DispatcherBuilder::new()
+            .add(failing)
+            .add(sys1)
+            .add(failing)
+            .build(&mut world);
+        match dispatch.par_run_with_scope(&world) {
+            Err(EcsError::DispatcherExecutionFailed(errors)) => assert_eq!(errors.len(), 2),
+            _ => unreachable!(),
+        }
+
+        // Errors keep the order of the stage, main-thread systems included.
+        let mut dispatch = DispatcherBuilder::new()
+            .add(|_a: &A| Err(EcsError::NotInitialized.context("first")))
+            .add_main_thread_system(
+                (|_a: &A| Err(EcsError::NotInitialized.context("second"))).system(),
+            )
+            .add(|_a: &A| Err(EcsError::NotInitialized.context("third")))
+            .build(&mut world);
+        match dispatch.par_run_with_scope(&world) {
+            Err(EcsError::DispatcherExecutionFailed(errors)) => assert_eq!(
+                errors.iter().map(|e| e.to_string()).collect::<Vec<_>>(),
+                vec![
+                    "first: resource is not initialized",
+                    "second: resource is not initialized",
+                    "third: resource is not initialized",
+                ]
+            ),
+            _ => unreachable!(),
+        }
+    }
+
     #[cfg(feature = "parallel")]
     #[test]
-    #[wasm_bindgen_test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
     fn dispatch_par_stages() {
         #[derive(Default)]
         struct A;
@@ -221,4 +1500,981 @@ mod tests {
         assert_eq!(dispatch.stages[0].len(), 1);
         dispatch.run_par(&world).unwrap();
     }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn flush_points() {
+        #[derive(Default)]
+        struct Seen(Vec<bool>);
+        fn spawn(commands: &Commands) -> SystemResult {
+            commands.push(|world: &mut World| world.initialize::<u64>());
+            Ok(())
+        }
+        fn check(world: &mut World) -> SystemResult {
+            let seen = world.get::<u64>().is_ok();
+            world.get_mut::<Seen>()?.0.push(seen);
+            Ok(())
+        }
+        let mut world = World::default();
+        world.initialize::<Seen>();
+        let mut dispatch = DispatcherBuilder::new()
+            .add(spawn)
+            .add_exclusive(check)
+            .add(|_: &mut Seen| Ok(()))
+            .add_exclusive(check)
+            .add_flush_point("spawned")
+            .add_exclusive(check)
+            .build(&mut world);
+        assert_eq!(dispatch.flush_points(), &["spawned"]);
+        dispatch.run(&mut world).unwrap();
+        assert_eq!(world.get::<Seen>().unwrap().0, vec![false, false, true]);
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn exclusive_and_commands() {
+        #[derive(Default)]
+        struct A(u32);
+        #[derive(Default)]
+        struct B(u32);
+        let mut world = World::default();
+        fn sys1(_a: &A, commands: &Commands) -> SystemResult {
+            commands.push(|world: &mut World| world.initialize::<B>());
+            Ok(())
+        }
+        fn sys2(_a: &A) -> SystemResult {
+            Ok(())
+        }
+        let mut dispatch = DispatcherBuilder::new()
+            .add(sys1)
+            .add_exclusive(|world: &mut World| {
+                world.get_mut::<B>()?.0 += 1;
+                Ok(())
+            })
+            .add(sys2)
+            .add(sys2)
+            .add_exclusive(|world: &mut World| {
+                world.get_mut::<A>()?.0 += 1;
+                Ok(())
+            })
+            .build(&mut world);
+        assert_eq!(dispatch.stages.len(), 2);
+        assert_eq!(dispatch.stages[0].len(), 1);
+        assert_eq!(dispatch.stages[1].len(), 2);
+        assert!(world.get::<B>().is_err());
+        dispatch.run(&mut world).unwrap();
+        dispatch.run(&mut world).unwrap();
+        assert_eq!(world.get::<A>().unwrap().0, 2);
+        assert_eq!(world.get::<B>().unwrap().0, 2);
+
+        // Exclusive systems are skipped.
+        dispatch.run_ref(&world).unwrap();
+        assert_eq!(world.get::<A>().unwrap().0, 2);
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn retry_system() {
+        let mut world = World::default();
+        let mut dispatch = DispatcherBuilder::new()
+            .add_system_retry(
+                5,
+                (|attempts: &mut u32| {
+                    *attempts += 1;
+                    match *attempts {
+                        3 => Ok(()),
+                        _ => Err(EcsError::NotInitialized),
+                    }
+                })
+                .system(),
+            )
+            .build(&mut world);
+        dispatch.run_seq(&world).unwrap();
+        assert_eq!(*world.get::<u32>().unwrap(), 3);
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn no_auto_initialize() {
+        #[derive(Default)]
+        struct A;
+        #[derive(Default)]
+        struct B;
+        let mut world = World::default();
+        world.initialize::<A>();
+        let mut dispatch = DispatcherBuilder::new()
+            .add(|_a: &A| Ok(()))
+            .add(|_a: &A, _b: &B| Ok(()))
+            .add(|_a: &A| Ok(()))
+            .auto_initialize(false)
+            .build(&mut world);
+        assert!(world.get::<B>().is_err());
+        assert_eq!(dispatch.stages.len(), 3);
+        assert_eq!(dispatch.stages[1].len(), 1);
+        match dispatch.run_seq(&world) {
+            Err(EcsError::DispatcherExecutionFailed(errors)) => {
+                assert_eq!(errors.len(), 1);
+                assert!(matches!(errors[0], EcsError::NotInitialized));
+            }
+            _ => unreachable!(),
+        }
+        world.initialize::<B>();
+        dispatch.run_seq(&world).unwrap();
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn run_criteria() {
+        #[derive(Default)]
+        struct Paused(bool);
+        let mut world = World::default();
+        let mut dispatch = DispatcherBuilder::new()
+            .add(|n: &mut u32| {
+                *n += 1;
+                Ok(())
+            })
+            .with_run_criteria(|world| !world.get::<Paused>().is_ok_and(|p| p.0))
+            .build(&mut world);
+        world.initialize::<Paused>();
+        dispatch.run(&mut world).unwrap();
+        world.get_mut::<Paused>().unwrap().0 = true;
+        dispatch.run(&mut world).unwrap();
+        dispatch.run_seq(&world).unwrap();
+        assert_eq!(*world.get::<u32>().unwrap(), 1);
+        world.get_mut::<Paused>().unwrap().0 = false;
+        dispatch.run_seq(&world).unwrap();
+        assert_eq!(*world.get::<u32>().unwrap(), 2);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn outstanding_borrows() {
+        let mut world = World::default();
+        let dispatch = DispatcherBuilder::new()
+            .add(|_: &u32, _: &mut u64| Ok(()))
+            .add(|_: &World| Ok(()))
+            .build(&mut world);
+        assert_eq!(world.outstanding_borrows(), 0);
+        let mut locks = vec![];
+        lock_arg::<u32>(&world, &mut locks).unwrap();
+        lock_arg_mut::<u64>(&world, &mut locks).unwrap();
+        assert_eq!(world.outstanding_borrows(), 2);
+        drop(locks);
+        assert_eq!(world.outstanding_borrows(), 0);
+        let _long_borrow = world.get::<u32>().unwrap();
+        assert_eq!(world.outstanding_borrows(), 0);
+        dispatch.dry_run(&world).unwrap();
+        assert_eq!(world.outstanding_borrows(), 0);
+    }
+
+    /// A `System::lock` forgetting the guard it extends.
+    #[cfg(debug_assertions)]
+    fn leaky_lock() -> LockFn {
+        std::sync::Arc::new(|world: *const World, _: *mut Vec<Box<dyn RefLifetime>>| {
+            let mut locks = vec![];
+            // Unsafe: the world outlives the call, like in the `lock` of the
+            // systems created by `IntoSystem`.
+            lock_arg::<u32>(unsafe { &*world }, &mut locks)?;
+            std::mem::forget(locks);
+            Ok(())
+        })
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "`DispatcherBuilder::build` leaked a borrow")]
+    fn leaked_lock_build() {
+        let mut world = World::default();
+        world.initialize::<u32>();
+        let mut sys = (|_: &u32| Ok(())).system();
+        sys.lock = leaky_lock();
+        DispatcherBuilder::new().add_system(sys).build(&mut world);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "leaked a borrow")]
+    fn leaked_lock_dry_run() {
+        let mut world = World::default();
+        let mut dispatch = DispatcherBuilder::new()
+            .add(|_: &u32| Ok(()))
+            .build(&mut world);
+        dispatch.stages[0][0].lock = leaky_lock();
+        let _ = dispatch.dry_run(&world);
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn dispatcher_label() {
+        let mut world = World::default();
+        let dispatch = DispatcherBuilder::new()
+            .add_system((|_: &u32| Ok(())).system().with_label("read"))
+            .add_shutdown_system((|_: &mut u32| Ok(())).system().with_label("save"))
+            .with_label("physics")
+            .build(&mut world);
+        assert_eq!(dispatch.label(), Some("physics"));
+        assert!(dispatch.contains_system("read"));
+        assert!(dispatch.contains_system("save"));
+        assert!(!dispatch.contains_system("physics"));
+        let debug = format!("{:?}", dispatch);
+        assert!(debug.starts_with("Dispatcher { label: Some(\"physics\"), stages: [["));
+        assert_eq!(DispatcherBuilder::new().build(&mut world).label(), None);
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn abort_on() {
+        #[derive(Default)]
+        struct AbortRequested(bool);
+        let mut world = World::default();
+        let mut dispatch = DispatcherBuilder::new()
+            .add(|n: &mut u32| {
+                *n += 1;
+                Ok(())
+            })
+            .add(|abort: &mut AbortRequested| {
+                abort.0 = true;
+                Ok(())
+            })
+            .add(|n: &mut u32| {
+                *n += 10;
+                Ok(())
+            })
+            .abort_on(|world| world.get::<AbortRequested>().is_ok_and(|a| a.0))
+            .build(&mut world);
+        dispatch.run(&mut world).unwrap();
+        assert_eq!(*world.get::<u32>().unwrap(), 1);
+        dispatch.run_seq(&world).unwrap();
+        assert_eq!(*world.get::<u32>().unwrap(), 1);
+        world.get_mut::<AbortRequested>().unwrap().0 = false;
+        dispatch.run_seq(&world).unwrap();
+        assert_eq!(*world.get::<u32>().unwrap(), 2);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "different `World`")]
+    fn other_world() {
+        let mut world = World::default();
+        let mut dispatch = DispatcherBuilder::new()
+            .add(|_: &u32| Ok(()))
+            .build(&mut world);
+        let mut other = World::default();
+        other.initialize::<u32>();
+        let _ = dispatch.run_seq(&other);
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn primitive_resources() {
+        #[derive(Default)]
+        struct Score;
+        let systems = [
+            (|_: &Score| Ok(())).system(),
+            (|_: &u32, _: &mut String| Ok(())).system(),
+        ];
+        let warnings = super::primitive_resource_warnings(systems.iter());
+        assert_eq!(warnings.len(), 2);
+        assert!(matches!(
+            &warnings[0],
+            ScheduleWarning::PrimitiveResource { resource, .. } if resource == "u32"
+        ));
+        assert!(matches!(
+            &warnings[1],
+            ScheduleWarning::PrimitiveResource { resource, .. }
+                if resource == "alloc::string::String"
+        ));
+        fn count(_: &u64) -> SystemResult {
+            Ok(())
+        }
+        let builder = || DispatcherBuilder::new().add(count).add(|_: &Score| Ok(()));
+        assert!(builder().validate().is_empty());
+        let warnings = builder().warn_on_primitive_resources().validate();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(
+            warnings[0].to_string(),
+            format!(
+                "System `{}` uses the primitive type `u64` as a resource, which other systems \
+                may use for something else. Consider wrapping it in a newtype.",
+                count.system().name()
+            )
+        );
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn write_never_read() {
+        #[derive(Default)]
+        struct Position;
+        #[derive(Default)]
+        struct Velocity;
+        #[derive(Default)]
+        struct Stats;
+        fn movement(_v: &Velocity, _p: &mut Position) -> SystemResult {
+            Ok(())
+        }
+        fn render(_p: &Position, _s: &mut Stats) -> SystemResult {
+            Ok(())
+        }
+        fn accelerate(_v: &mut Velocity, _s: &mut Stats) -> SystemResult {
+            Ok(())
+        }
+        let builder = || {
+            DispatcherBuilder::new()
+                .add(movement)
+                .add(render)
+                .add(accelerate)
+        };
+        let warnings = builder().validate();
+        assert_eq!(warnings.len(), 2);
+        assert!(matches!(
+            &warnings[0],
+            ScheduleWarning::WriteNeverRead { system, resource }
+                if system.ends_with("render") && resource.ends_with("Stats")
+        ));
+        assert!(warnings[1].to_string().contains("accelerate"));
+        assert!(builder()
+            .allow_write_never_read::<Stats>()
+            .validate()
+            .is_empty());
+        assert!(builder().add(|_w: &World| Ok(())).validate().is_empty());
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn set_enabled() {
+        #[derive(Default)]
+        struct Log(Vec<&'static str>);
+        fn a(log: &mut Log) -> SystemResult {
+            log.0.push("a");
+            Ok(())
+        }
+        fn b(log: &mut Log) -> SystemResult {
+            log.0.push("b");
+            Ok(())
+        }
+        let mut world = World::default();
+        let mut dispatch = DispatcherBuilder::new()
+            .add_system(a.system().with_label("a"))
+            .add_system(b.system().with_label("b"))
+            .add_system(a.system())
+            .build(&mut world);
+        assert!(dispatch.is_enabled("b"));
+        assert!(!dispatch.is_enabled("c"));
+        assert!(!dispatch.set_enabled("c", false));
+        assert!(dispatch.set_enabled("b", false));
+        assert!(!dispatch.is_enabled("b"));
+        dispatch.run(&mut world).unwrap();
+        assert_eq!(world.get::<Log>().unwrap().0, vec!["a", "a"]);
+        world.get_mut::<Log>().unwrap().0.clear();
+        let progress = dispatch
+            .run_with_budget(&world, std::time::Duration::from_secs(60))
+            .unwrap();
+        assert!(progress.is_finished());
+        assert_eq!(world.get::<Log>().unwrap().0, vec!["a", "a"]);
+        world.get_mut::<Log>().unwrap().0.clear();
+        assert!(dispatch.set_enabled("b", true));
+        dispatch.run_seq(&world).unwrap();
+        assert_eq!(world.get::<Log>().unwrap().0, vec!["a", "b", "a"]);
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn add_systems_tuple() {
+        #[derive(Default)]
+        struct Log(Vec<&'static str>);
+        fn a(log: &mut Log) -> SystemResult {
+            log.0.push("a");
+            Ok(())
+        }
+        fn b(_: &u32, log: &mut Log) -> SystemResult {
+            log.0.push("b");
+            Ok(())
+        }
+        let mut world = World::default();
+        let mut dispatch = DispatcherBuilder::new()
+            .add_systems((a, b, |log: &mut Log| {
+                log.0.push("c");
+                Ok(())
+            }))
+            .build(&mut world);
+        dispatch.run_seq(&world).unwrap();
+        assert_eq!(world.get::<Log>().unwrap().0, vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn initialization_order() {
+        #[derive(Default)]
+        struct A;
+        fn startup(value: u32) -> System {
+            System {
+                initialize: std::sync::Arc::new(move |world: &mut World| {
+                    world.insert_if_absent(value);
+                }),
+                lock: std::sync::Arc::new(|_, _| Ok(())),
+                run_fn: Box::new(|_| Ok(())),
+                run_factory: None,
+                name: "startup",
+                access: Access {
+                    writes: vec![TypeId::of::<u32>()],
+                    write_names: vec![World::type_name::<u32>()],
+                    ..Access::default()
+                },
+                label: None,
+                main_thread: false,
+            }
+        }
+        let mut world = World::default();
+        let dispatch = DispatcherBuilder::new()
+            .add(|_a: &A| Ok(()))
+            .add_system(startup(1))
+            .add_system(startup(2))
+            .add_shutdown_system((|_a: &A, _b: &mut u64| Ok(())).system())
+            .build(&mut world);
+        assert_eq!(*world.get::<u32>().unwrap(), 1);
+        assert_eq!(
+            dispatch.initialization_order(),
+            vec![
+                World::type_name::<A>(),
+                World::type_name::<u32>(),
+                World::type_name::<u64>()
+            ]
+        );
+        let dispatch = DispatcherBuilder::new()
+            .add_system(startup(3))
+            .build(&mut world);
+        assert_eq!(*world.get::<u32>().unwrap(), 1);
+        assert!(dispatch.initialization_order().is_empty());
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn dry_run() {
+        #[derive(Default)]
+        struct A;
+        #[derive(Default)]
+        struct B;
+        fn sys1(_a: &A) -> SystemResult {
+            Ok(())
+        }
+        fn sys2(_a: &A, _b: &mut B) -> SystemResult {
+            panic!("dry runs don't run systems");
+        }
+        let mut world = World::default();
+        let dispatch = DispatcherBuilder::new()
+            .add(sys1)
+            .add(sys2)
+            .build(&mut world);
+        let ticks = (world.get_change_tick::<A>(), world.get_change_tick::<B>());
+        #[cfg(feature = "access_stats")]
+        world.reset_access_stats();
+        dispatch.dry_run(&world).unwrap();
+        assert_eq!(
+            (world.get_change_tick::<A>(), world.get_change_tick::<B>()),
+            ticks
+        );
+        #[cfg(feature = "access_stats")]
+        assert!(world.access_stats().is_empty());
+
+        let _long_borrow = world.get::<B>().unwrap();
+        let errors = dispatch.dry_run(&World::default()).unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(errors[0].1, EcsError::NotInitialized));
+        let errors = dispatch.dry_run(&world).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].0.contains("sys2"));
+        assert!(!matches!(errors[0].1, EcsError::NotInitialized));
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn build_keeps_change_ticks() {
+        let mut world = World::default();
+        world.register_change_detect::<u32>();
+        world.initialize::<u32>();
+        world.initialize::<u64>();
+        let ticks = (
+            world.get_change_tick::<u32>(),
+            world.get_change_tick::<u64>(),
+        );
+        let _dispatch = DispatcherBuilder::new()
+            .add(|_: &u64, _: &mut u32| Ok(()))
+            .add(|_: &u32, _: &mut u64| Ok(()))
+            .build(&mut world);
+        assert_eq!(
+            (
+                world.get_change_tick::<u32>(),
+                world.get_change_tick::<u64>()
+            ),
+            ticks
+        );
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn shutdown_systems() {
+        #[derive(Default)]
+        struct A(u32);
+        #[derive(Default)]
+        struct Flushed(Vec<u32>);
+        let mut world = World::default();
+        let mut dispatch = DispatcherBuilder::new()
+            .add(|a: &mut A| {
+                a.0 += 1;
+                Ok(())
+            })
+            .add_shutdown_system(
+                (|a: &A, flushed: &mut Flushed| {
+                    flushed.0.push(a.0);
+                    Ok(())
+                })
+                .system(),
+            )
+            .build(&mut world);
+        assert!(world.get::<Flushed>().is_ok());
+        dispatch.run_seq(&world).unwrap();
+        dispatch.run_seq(&world).unwrap();
+        assert!(world.get::<Flushed>().unwrap().0.is_empty());
+        dispatch.shutdown(&world).unwrap();
+        dispatch.shutdown(&world).unwrap();
+        assert_eq!(world.get::<Flushed>().unwrap().0, vec![2]);
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn add_system_unique() {
+        #[derive(Default)]
+        struct A(u32);
+        fn sys(a: &mut A) -> SystemResult {
+            a.0 += 1;
+            Ok(())
+        }
+        let mut world = World::default();
+        let mut builder = DispatcherBuilder::new();
+        assert!(builder.add_system_unique("count", sys.system()));
+        assert!(!builder.add_system_unique("count", sys.system()));
+        assert!(builder.add_system_unique("other", sys.system()));
+        assert_eq!(builder.systems[0].label(), Some("count"));
+        let mut dispatch = builder.build(&mut world);
+        dispatch.run_seq(&world).unwrap();
+        assert_eq!(world.get::<A>().unwrap().0, 2);
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn run_until() {
+        #[derive(Default)]
+        struct Estimate(f64);
+        // Newton's method for the square root of 2.
+        fn step(x: &mut Estimate) -> SystemResult {
+            x.0 = (x.0 + 2.0 / x.0) / 2.0;
+            Ok(())
+        }
+        let mut world = World::default();
+        let mut dispatch = DispatcherBuilder::new().add(step).build(&mut world);
+        world.get_mut::<Estimate>().unwrap().0 = 1.0;
+        let converged =
+            |world: &World| (world.get::<Estimate>().unwrap().0.powi(2) - 2.0).abs() < 1e-9;
+        assert_eq!(dispatch.run_until(&world, 100, converged).unwrap(), 4);
+        world.get_mut::<Estimate>().unwrap().0 = 1000.0;
+        assert!(matches!(
+            dispatch.run_until(&world, 3, converged),
+            Err(EcsError::MaxIterationsExceeded(3))
+        ));
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn schedule_digest() {
+        #[derive(Default)]
+        struct A;
+        fn read(_a: &A) -> SystemResult {
+            Ok(())
+        }
+        fn write(_a: &mut A) -> SystemResult {
+            Ok(())
+        }
+        let mut world = World::default();
+        let dispatch = DispatcherBuilder::new()
+            .add(read)
+            .add_system(read.system().with_label("second_read"))
+            .add(write)
+            .add_exclusive(|_| Ok(()))
+            .add(read)
+            .add_shutdown_system(write.system().with_label("cleanup"))
+            .build(&mut world);
+        let read_name = read.system().name();
+        let write_name = write.system().name();
+        assert_eq!(
+            dispatch.schedule_digest(),
+            format!(
+                "stage 0\n  {}\n  second_read\nstage 1\n  {}\nexclusive 1\nstage 2\n  {}\nshutdown\n  cleanup\n",
+                read_name, write_name, read_name
+            )
+        );
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn auto_parallelize() {
+        #[derive(Default)]
+        struct A;
+        #[derive(Default)]
+        struct B;
+        #[derive(Default)]
+        struct C;
+        fn write_a(_a: &mut A) -> SystemResult {
+            Ok(())
+        }
+        fn write_b(_b: &mut B) -> SystemResult {
+            Ok(())
+        }
+        fn read_a(_a: &A) -> SystemResult {
+            Ok(())
+        }
+        fn write_c(_c: &mut C) -> SystemResult {
+            Ok(())
+        }
+        fn read_world(_world: &World) -> SystemResult {
+            Ok(())
+        }
+        let name = |f: System| f.name();
+        let builder = || {
+            DispatcherBuilder::new()
+                .add(write_a)
+                .add(write_b)
+                .add(read_a)
+                .add(write_c)
+                .add_exclusive(|_| Ok(()))
+                .add(read_a)
+                .add(read_world)
+                .add(write_b)
+        };
+        let mut world = World::default();
+        let mut dispatch = builder().build(&mut world);
+        assert_eq!(dispatch.waves().len(), 4);
+        dispatch.run(&mut world).unwrap();
+        let mut dispatch = builder().auto_parallelize().build(&mut world);
+        assert_eq!(
+            dispatch.waves(),
+            vec![
+                vec![
+                    name(write_a.system()),
+                    name(write_b.system()),
+                    name(write_c.system())
+                ],
+                vec![name(read_a.system())],
+                vec![name(read_a.system()), name(read_world.system())],
+                vec![name(write_b.system())],
+            ]
+        );
+        dispatch.run(&mut world).unwrap();
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn main_thread_system() {
+        use std::thread::ThreadId;
+        fn pinned(thread: &mut Option<ThreadId>) -> SystemResult {
+            *thread = Some(std::thread::current().id());
+            Ok(())
+        }
+        let mut world = World::default();
+        let mut builder = DispatcherBuilder::new().add_main_thread_system(pinned.system());
+        for _ in 0..8 {
+            builder = builder.add(|_a: &u32| Ok(()));
+        }
+        let mut dispatch = builder.build(&mut world);
+        assert_eq!(dispatch.waves().len(), 1);
+        let main = Some(std::thread::current().id());
+        for _ in 0..4 {
+            *world.get_mut::<Option<ThreadId>>().unwrap() = None;
+            dispatch.run(&mut world).unwrap();
+            assert_eq!(*world.get::<Option<ThreadId>>().unwrap(), main);
+            #[cfg(feature = "parallel")]
+            {
+                *world.get_mut::<Option<ThreadId>>().unwrap() = None;
+                dispatch.run_par(&world).unwrap();
+                assert_eq!(*world.get::<Option<ThreadId>>().unwrap(), main);
+            }
+            #[cfg(feature = "std_parallel")]
+            {
+                *world.get_mut::<Option<ThreadId>>().unwrap() = None;
+                dispatch.par_run_with_scope(&world).unwrap();
+                assert_eq!(*world.get::<Option<ThreadId>>().unwrap(), main);
+            }
+        }
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn run_filtered() {
+        fn physics_move(log: &mut Vec<&'static str>) -> SystemResult {
+            log.push("move");
+            Ok(())
+        }
+        fn render(log: &mut Vec<&'static str>) -> SystemResult {
+            log.push("render");
+            Ok(())
+        }
+        fn physics_collide(log: &mut Vec<&'static str>) -> SystemResult {
+            log.push("collide");
+            Ok(())
+        }
+        let mut world = World::default();
+        let mut dispatch = DispatcherBuilder::new()
+            .add(physics_move)
+            .add(render)
+            .add(physics_collide)
+            .build(&mut world);
+        dispatch
+            .run_filtered(&world, |name| name.contains("physics"))
+            .unwrap();
+        assert_eq!(
+            *world.get::<Vec<&'static str>>().unwrap(),
+            vec!["move", "collide"]
+        );
+        dispatch.run_filtered(&world, |_| false).unwrap();
+        assert_eq!(world.get::<Vec<&'static str>>().unwrap().len(), 2);
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn step() {
+        fn first(v: &mut u32) -> SystemResult {
+            *v += 1;
+            Ok(())
+        }
+        fn second(v: &u32) -> SystemResult {
+            ensure!(*v > 1, "not yet");
+            Ok(())
+        }
+        fn third(v: &mut u32) -> SystemResult {
+            *v *= 10;
+            Ok(())
+        }
+        let mut world = World::default();
+        let mut dispatch = DispatcherBuilder::new()
+            .add(first)
+            .add(second)
+            .add(third)
+            .build(&mut world);
+        let (name, result) = dispatch.step(&world).unwrap();
+        assert!(name.ends_with("first"));
+        assert!(result.is_ok());
+        assert_eq!(*world.get::<u32>().unwrap(), 1);
+        let (name, result) = dispatch.step(&world).unwrap();
+        assert!(name.ends_with("second"));
+        assert!(result.is_err());
+        let (name, result) = dispatch.step(&world).unwrap();
+        assert!(name.ends_with("third"));
+        assert!(result.is_ok());
+        assert_eq!(*world.get::<u32>().unwrap(), 10);
+        assert!(dispatch.step(&world).is_none());
+        assert!(dispatch.step(&world).is_none());
+
+        dispatch.run(&mut world).unwrap();
+        assert_eq!(*world.get::<u32>().unwrap(), 110);
+        assert!(dispatch.step(&world).unwrap().0.ends_with("first"));
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn run_with_budget() {
+        #[derive(Default)]
+        struct Log(Vec<u32>);
+        let mut world = World::default();
+        let mut dispatch = DispatcherBuilder::new()
+            .add_system(
+                (|log: &mut Log| {
+                    log.0.push(1);
+                    Ok(())
+                })
+                .system()
+                .with_label("first"),
+            )
+            .add(|log: &mut Log| {
+                log.0.push(2);
+                Ok(())
+            })
+            .add(|log: &mut Log| {
+                log.0.push(3);
+                Ok(())
+            })
+            .build(&mut world);
+        let zero = std::time::Duration::from_secs(0);
+        let progress = dispatch.run_with_budget(&world, zero).unwrap();
+        assert_eq!(
+            progress,
+            RunProgress {
+                ran: 1,
+                remaining: 2
+            }
+        );
+        assert!(!progress.is_finished());
+        assert_eq!(world.get::<Log>().unwrap().0, vec![1]);
+        dispatch.run_with_budget(&world, zero).unwrap();
+        let progress = dispatch.run_with_budget(&world, zero).unwrap();
+        assert!(progress.is_finished());
+        assert_eq!(world.get::<Log>().unwrap().0, vec![1, 2, 3]);
+        let hour = std::time::Duration::from_secs(3600);
+        let progress = dispatch.run_with_budget(&world, hour).unwrap();
+        assert_eq!(
+            progress,
+            RunProgress {
+                ran: 3,
+                remaining: 0
+            }
+        );
+        assert_eq!(world.get::<Log>().unwrap().0, vec![1, 2, 3, 1, 2, 3]);
+
+        world.get_mut::<Log>().unwrap().0.clear();
+        dispatch.set_enabled("first", false);
+        let progress = dispatch.run_with_budget(&world, zero).unwrap();
+        assert_eq!(
+            progress,
+            RunProgress {
+                ran: 1,
+                remaining: 1
+            }
+        );
+        assert_eq!(world.get::<Log>().unwrap().0, vec![2]);
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn run_with_budget_error() {
+        let mut world = World::default();
+        let mut dispatch = DispatcherBuilder::new()
+            .add(|_: &u32| Ok(()))
+            .add(|_: &u32| Err(EcsError::NotInitialized.context("second")))
+            .add(|_: &u32| Ok(()))
+            .build(&mut world);
+        let zero = std::time::Duration::from_secs(0);
+        dispatch.run_with_budget(&world, zero).unwrap();
+        let (progress, error) = dispatch.run_with_budget(&world, zero).unwrap_err();
+        assert_eq!(
+            progress,
+            RunProgress {
+                ran: 1,
+                remaining: 1
+            }
+        );
+        match error {
+            EcsError::DispatcherExecutionFailed(errors) => assert_eq!(errors.len(), 1),
+            _ => unreachable!(),
+        }
+        let hour = std::time::Duration::from_secs(3600);
+        let progress = dispatch.run_with_budget(&world, hour).unwrap();
+        assert!(progress.is_finished());
+        let (progress, _) = dispatch.run_with_budget(&world, hour).unwrap_err();
+        assert_eq!(
+            progress,
+            RunProgress {
+                ran: 3,
+                remaining: 0
+            }
+        );
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn extend_builder() {
+        #[derive(Default)]
+        struct A(Vec<u32>);
+        let mut world = World::default();
+        let first = DispatcherBuilder::new()
+            .add(|a: &mut A| {
+                a.0.push(1);
+                Ok(())
+            })
+            .add_exclusive(|world: &mut World| {
+                world.get_mut::<A>()?.0.push(2);
+                Ok(())
+            });
+        let second = DispatcherBuilder::new()
+            .add_exclusive(|world: &mut World| {
+                world.get_mut::<A>()?.0.push(3);
+                Ok(())
+            })
+            .add(|a: &mut A| {
+                a.0.push(4);
+                Ok(())
+            })
+            .add_shutdown_system(
+                (|a: &mut A| {
+                    a.0.push(5);
+                    Ok(())
+                })
+                .system(),
+            );
+        let mut dispatch = first.extend(second).build(&mut world);
+        dispatch.run(&mut world).unwrap();
+        dispatch.shutdown(&world).unwrap();
+        assert_eq!(world.get::<A>().unwrap().0, vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn extend_builder_settings() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        #[derive(Default)]
+        struct Paused(bool);
+        fn count(c: &mut u32) -> SystemResult {
+            *c += 1;
+            Ok(())
+        }
+        let runs = Arc::new(AtomicUsize::new(0));
+        let hook_runs = runs.clone();
+        let module = DispatcherBuilder::new()
+            .add_system(count.system().with_label("count"))
+            .on_system_run(move |event| {
+                if event.phase == SystemRunPhase::Before {
+                    hook_runs.fetch_add(1, Ordering::SeqCst);
+                }
+            })
+            .with_run_criteria(|world| !world.get::<Paused>().unwrap().0)
+            .with_label("game");
+        let mut world = World::default();
+        world.initialize::<Paused>();
+        let mut dispatch = DispatcherBuilder::new()
+            .add(|_a: &Paused| Ok(()))
+            .extend(module)
+            .build(&mut world);
+        assert_eq!(dispatch.label(), Some("game"));
+        dispatch.run(&mut world).unwrap();
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+        world.get_mut::<Paused>().unwrap().0 = true;
+        dispatch.run(&mut world).unwrap();
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+        assert_eq!(*world.get::<u32>().unwrap(), 1);
+
+        let labeled = || DispatcherBuilder::new().add_system(count.system().with_label("count"));
+        match labeled().extend(labeled()).try_build(&mut world) {
+            Err(e @ ScheduleError::DuplicateLabel { .. }) => assert_eq!(
+                e.to_string(),
+                "The label `count` is used by systems of several merged builders."
+            ),
+            _ => unreachable!(),
+        }
+        // Labels shared within a single builder are allowed.
+        assert!(labeled()
+            .add_system(count.system().with_label("count"))
+            .extend(DispatcherBuilder::new())
+            .try_build(&mut world)
+            .is_ok());
+        match labeled()
+            .extend(DispatcherBuilder::new().auto_initialize(false))
+            .try_build(&mut world)
+        {
+            Err(e @ ScheduleError::Contradiction { .. }) => assert_eq!(
+                e.to_string(),
+                "The merged builders use different values of `auto_initialize`."
+            ),
+            _ => unreachable!(),
+        }
+    }
 }
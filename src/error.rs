@@ -9,43 +9,607 @@ pub enum EcsError {
     /// Usually, this means no dispatcher was used and `World::initialize`
     /// was not called.
     NotInitialized,
+    /// Like `NotInitialized`, but identifies the missing resource.
+    ///
+    /// Returned by the functions borrowing multiple resources at once, such as
+    /// `World::get_many_refs`, and by `System::validate_against`.
+    ResourceNotFound {
+        /// The type name of the resource.
+        type_name: &'static str,
+    },
+    /// The resource was not registered as the requested trait object type,
+    /// see `World::register_dyn`.
+    NotRegistered {
+        /// The type name of the resource.
+        type_name: &'static str,
+        /// The type name of the trait object type.
+        as_type: &'static str,
+    },
     /// The requested resource is already borrowed.
     /// This error is created if the `System` tries to read a resource that
     /// has already been mutably borrowed.
     /// It can also happen when trying to mutably borrow a resource that is
-    /// already being read.
+    /// already being read, although `World::get_mut` returns
+    /// `AlreadySharedBorrowed` in that case.
     ///
     /// This error should not occur during normal use, as the dispatchers
     /// can recover easily.
     AlreadyBorrowed,
+    /// The resource can't be borrowed mutably by `World::get_mut`, because
+    /// it is already borrowed immutably, possibly by an enclosing scope.
+    /// Returned instead of `AlreadyBorrowedBy` with the `debug_borrows`
+    /// feature.
+    AlreadySharedBorrowed {
+        /// The type name of the resource.
+        type_name: &'static str,
+    },
+    /// The requested resource is already borrowed, by the given `Borrower`
+    /// that still holds the borrow. The oldest one is given when there are
+    /// several.
+    ///
+    /// This replaces `AlreadyBorrowed` in `World::get` and `World::get_mut`
+    /// when the `debug_borrows` feature is enabled.
+    #[cfg(feature = "debug_borrows")]
+    AlreadyBorrowedBy(Borrower),
+    /// The same resource was requested more than once in a single call, at
+    /// least once mutably.
+    ConflictingAccess {
+        /// The type name of the resource.
+        type_name: &'static str,
+    },
+    /// The dispatcher ran the given number of times without reaching the
+    /// expected state, see `Dispatcher::run_until`.
+    ///
+    /// Also returned by a system that still asks to be repeated after the
+    /// given number of repeats, see `System::repeating`.
+    MaxIterationsExceeded(usize),
     /// The execution of the dispatcher failed and returned one or more errors.
     DispatcherExecutionFailed(Vec<EcsError>),
     /// This variant is for user-defined errors.
     /// To create an error of this type easily, use the `system_error!` macro.
-    SystemError(Box<dyn Error+Send>),
+    SystemError(Box<dyn Error + Send>),
+    /// An error wrapped with a message describing what was being done when
+    /// it happened, see `EcsError::context`.
+    Context {
+        /// The message describing what was being done.
+        context: String,
+        /// The wrapped error.
+        source: Box<EcsError>,
+    },
+}
+
+impl EcsError {
+    /// Wraps the error with a message describing what was being done when it
+    /// happened, such as "while loading the config". The error is kept as the
+    /// `source` of the new one, and both are displayed, outermost first.
+    /// ```
+    /// use world_dispatcher::*;
+    ///
+    /// let error = EcsError::NotInitialized.context("while loading the config");
+    /// assert_eq!(
+    ///     error.to_string(),
+    ///     "while loading the config: resource is not initialized"
+    /// );
+    /// ```
+    pub fn context(self, context: impl std::fmt::Display) -> EcsError {
+        EcsError::Context {
+            context: context.to_string(),
+            source: Box::new(self),
+        }
+    }
+
+    /// Returns the user-defined error of type `E` returned by a system, if
+    /// this is an `EcsError::SystemError` holding one.
+    ///
+    /// Errors wrapped by `EcsError::context` are looked through, and for an
+    /// `EcsError::DispatcherExecutionFailed`, the first of its errors holding
+    /// an `E` is returned.
+    pub fn downcast_ref<E: Error + 'static>(&self) -> Option<&E> {
+        match self {
+            EcsError::SystemError(error) => error.downcast_ref::<E>(),
+            EcsError::Context { source, .. } => source.downcast_ref::<E>(),
+            EcsError::DispatcherExecutionFailed(errors) => {
+                errors.iter().find_map(|error| error.downcast_ref::<E>())
+            }
+            _ => None,
+        }
+    }
+
+    /// Takes the user-defined error of type `E` out of the error, looking for
+    /// it like `downcast_ref`. Gives back the error as is if it doesn't hold
+    /// an `E`.
+    /// ```
+    /// use world_dispatcher::*;
+    ///
+    /// #[derive(Debug)]
+    /// struct OutOfFuel;
+    /// impl std::fmt::Display for OutOfFuel {
+    ///     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    ///         write!(f, "out of fuel")
+    ///     }
+    /// }
+    /// impl std::error::Error for OutOfFuel {}
+    ///
+    /// let error = EcsError::SystemError(Box::new(OutOfFuel));
+    /// assert!(error.downcast::<OutOfFuel>().is_ok());
+    /// ```
+    pub fn downcast<E: Error + 'static>(self) -> Result<E, EcsError> {
+        if self.downcast_ref::<E>().is_none() {
+            return Err(self);
+        }
+        match self {
+            EcsError::SystemError(error) => Ok(*error.downcast::<E>().unwrap()),
+            EcsError::Context { source, .. } => source.downcast::<E>(),
+            EcsError::DispatcherExecutionFailed(mut errors) => {
+                let i = errors
+                    .iter()
+                    .position(|error| error.downcast_ref::<E>().is_some())
+                    .unwrap();
+                errors.swap_remove(i).downcast::<E>()
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl std::fmt::Display for EcsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            EcsError::NotInitialized => write!(f, "resource is not initialized"),
+            EcsError::ResourceNotFound { type_name } => {
+                write!(f, "resource `{}` is not in the world", type_name)
+            }
+            EcsError::NotRegistered { type_name, as_type } => {
+                write!(
+                    f,
+                    "resource `{}` is not registered as `{}`",
+                    type_name, as_type
+                )
+            }
+            EcsError::AlreadyBorrowed => write!(f, "resource is already borrowed"),
+            EcsError::AlreadySharedBorrowed { type_name } => write!(
+                f,
+                "cannot borrow `{}` mutably because it is already shared-borrowed \
+                (possibly by an enclosing scope)",
+                type_name
+            ),
+            #[cfg(feature = "debug_borrows")]
+            EcsError::AlreadyBorrowedBy(borrower) => {
+                write!(f, "resource is already borrowed at {}", borrower.location)?;
+                if let Some(system) = borrower.system {
+                    write!(f, " by system `{}`", system)?;
+                }
+                Ok(())
+            }
+            EcsError::ConflictingAccess { type_name } => write!(
+                f,
+                "resource `{}` is borrowed more than once, at least once mutably",
+                type_name
+            ),
+            EcsError::MaxIterationsExceeded(iterations) => {
+                write!(
+                    f,
+                    "the expected state was not reached after {} iterations",
+                    iterations
+                )
+            }
+            EcsError::DispatcherExecutionFailed(errors) => {
+                write!(f, "{} system(s) failed", errors.len())?;
+                for (i, error) in errors.iter().enumerate() {
+                    write!(f, "{} {}", if i == 0 { ":" } else { ";" }, error)?;
+                }
+                Ok(())
+            }
+            EcsError::SystemError(error) => error.fmt(f),
+            EcsError::Context { context, source } => write!(f, "{}: {}", context, source),
+        }
+    }
+}
+
+impl Error for EcsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            EcsError::SystemError(error) => Some(error.as_ref()),
+            EcsError::Context { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "anyhow")]
+impl From<anyhow::Error> for EcsError {
+    fn from(e: anyhow::Error) -> Self {
+        let e: Box<dyn Error + Send + Sync> = e.into();
+        EcsError::SystemError(e)
+    }
+}
+
+/// The reasons why the systems of a `DispatcherBuilder` can't be scheduled,
+/// returned by `DispatcherBuilder::try_build`.
+///
+/// Systems are scheduled in the order they were added, so there are no
+/// ordering constraints which could form cycles or name unknown labels.
+/// Adding a system with `DispatcherBuilder::add_system_unique` under a label
+/// that is already used isn't an error either: the system is skipped.
+#[derive(Debug)]
+pub enum ScheduleError {
+    /// The system borrows the resource more than once, at least once
+    /// mutably, so it can never run.
+    Conflict {
+        /// The name of the system.
+        system: String,
+        /// The type name of the resource.
+        resource: String,
+    },
+    /// The system can't borrow its resources from the `World`, even alone in
+    /// its stage, for example because a borrow was leaked.
+    Unborrowable {
+        /// The name of the system.
+        system: String,
+        /// The error returned when borrowing the resources.
+        error: EcsError,
+    },
+    /// Systems of two builders merged by `DispatcherBuilder::extend` use the
+    /// same label.
+    DuplicateLabel {
+        /// The label.
+        label: String,
+    },
+    /// Two builders merged by `DispatcherBuilder::extend` use different
+    /// values for a setting which applies to the whole `Dispatcher`.
+    Contradiction {
+        /// The name of the setting.
+        setting: &'static str,
+    },
+    /// Exclusive systems can't be used in a `DispatcherTemplate`, nor copied
+    /// by `Dispatcher::to_builder`.
+    #[cfg(feature = "sync_systems")]
+    ExclusiveInTemplate,
+    /// The system has no `run_factory`, so it can't be used in a
+    /// `DispatcherTemplate`.
+    #[cfg(feature = "sync_systems")]
+    NotShareable {
+        /// The name of the system.
+        system: String,
+    },
+}
+
+impl std::fmt::Display for ScheduleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ScheduleError::Conflict { system, resource } => write!(
+                f,
+                "System `{}` cannot be borrowed at all, because it uses `{}` twice in its \
+                signature, at least once mutably.",
+                system, resource
+            ),
+            ScheduleError::Unborrowable { system, error } => {
+                write!(
+                    f,
+                    "System `{}` cannot be borrowed at all: {}.",
+                    system, error
+                )
+            }
+            ScheduleError::DuplicateLabel { label } => write!(
+                f,
+                "The label `{}` is used by systems of several merged builders.",
+                label
+            ),
+            ScheduleError::Contradiction { setting } => write!(
+                f,
+                "The merged builders use different values of `{}`.",
+                setting
+            ),
+            #[cfg(feature = "sync_systems")]
+            ScheduleError::ExclusiveInTemplate => {
+                write!(f, "Exclusive systems cannot be used in a template.")
+            }
+            #[cfg(feature = "sync_systems")]
+            ScheduleError::NotShareable { system } => write!(
+                f,
+                "System `{}` cannot be used in a template, because it has no `run_factory`.",
+                system
+            ),
+        }
+    }
+}
+
+impl Error for ScheduleError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ScheduleError::Unborrowable { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Advisories about the systems of a `DispatcherBuilder`, returned by
+/// `DispatcherBuilder::validate`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScheduleWarning {
+    /// The system writes to the resource, but no system reads it, which is
+    /// often a sign of dead work. This can be expected when the resource is
+    /// read outside of the dispatcher, see
+    /// `DispatcherBuilder::allow_write_never_read`.
+    WriteNeverRead {
+        /// The name of the system.
+        system: String,
+        /// The type name of the resource.
+        resource: String,
+    },
+    /// The system uses a primitive type as a resource, see
+    /// `DispatcherBuilder::warn_on_primitive_resources`.
+    PrimitiveResource {
+        /// The name of the system.
+        system: String,
+        /// The type name of the resource.
+        resource: String,
+    },
+}
+
+impl std::fmt::Display for ScheduleWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ScheduleWarning::WriteNeverRead { system, resource } => write!(
+                f,
+                "System `{}` writes to `{}`, which no system reads.",
+                system, resource
+            ),
+            ScheduleWarning::PrimitiveResource { system, resource } => write!(
+                f,
+                "System `{}` uses the primitive type `{}` as a resource, which other systems \
+                may use for something else. Consider wrapping it in a newtype.",
+                system, resource
+            ),
+        }
+    }
+}
+
+/// The error returned by `World::insert_boxed_checked`.
+pub enum BoxedError {
+    /// A resource of the same type is already present. The rejected value is
+    /// given back.
+    AlreadyPresent(Box<dyn Resource>),
+}
+
+impl std::fmt::Debug for BoxedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            BoxedError::AlreadyPresent(value) => f
+                .debug_tuple("AlreadyPresent")
+                .field(&(**value).resource_type_name())
+                .finish(),
+        }
+    }
 }
 
 /// The result of a `System`'s execution.
 /// Returns Ok(()) on success, `EcsError` on failure.
 /// To return a custom error from a system, use the
 /// `system_error!` macro.
-pub type SystemResult = Result<(), EcsError>;
+///
+/// Systems producing a value return a `SystemResult<T>` instead.
+pub type SystemResult<T = ()> = Result<T, EcsError>;
 
 /// Returns a custom error from a `System` during execution.
 #[macro_export]
 macro_rules! system_error {
     ($err:expr) => {
-        return Err(EcsError::SystemError(Box::new($err)));
+        return Err(EcsError::SystemError(Box::new($err)))
+    };
+}
+
+/// Returns an error from a `System` during execution if a condition
+/// doesn't hold.
+///
+/// The error is either a formatted message, returned as an
+/// `EcsError::SystemError`, or any `EcsError`:
+/// ```
+/// use world_dispatcher::*;
+///
+/// fn pop_job(jobs: &mut Vec<u32>) -> SystemResult {
+///     ensure!(!jobs.is_empty(), "no job left in a queue of capacity {}", jobs.capacity());
+///     jobs.pop();
+///     Ok(())
+/// }
+///
+/// fn next_job(jobs: &Vec<u32>) -> SystemResult<u32> {
+///     ensure!(!jobs.is_empty(), EcsError::NotInitialized);
+///     Ok(jobs[jobs.len() - 1])
+/// }
+///
+/// let mut world = World::default();
+/// let mut pop = pop_job.system();
+/// pop.initialize(&mut world);
+/// assert!(pop.run(&world).is_err());
+/// world.get_mut::<Vec<u32>>().unwrap().push(1);
+/// assert_eq!(next_job.system().run(&world).unwrap(), 1);
+/// assert!(pop.run(&world).is_ok());
+/// ```
+#[macro_export]
+macro_rules! ensure {
+    ($cond:expr, $fmt:literal $(, $arg:expr)* $(,)?) => {
+        if !$cond {
+            let message: Box<dyn std::error::Error + Send + Sync> = format!($fmt $(, $arg)*).into();
+            return Err($crate::EcsError::SystemError(message));
+        }
+    };
+    ($cond:expr, $err:expr $(,)?) => {
+        if !$cond {
+            return Err($err);
+        }
     };
 }
 
 #[cfg(test)]
 mod tests {
     use crate::*;
+    #[cfg(target_arch = "wasm32")]
     use wasm_bindgen_test::*;
 
     #[test]
-    #[wasm_bindgen_test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn ensure_condition() {
+        #[derive(Default)]
+        struct Queue(Vec<u32>);
+        fn sys(queue: &Queue) -> SystemResult {
+            ensure!(queue.0.len() < 2, EcsError::AlreadyBorrowed);
+            ensure!(!queue.0.is_empty(), "queue of {} is empty", "u32");
+            Ok(())
+        }
+        let mut world = World::default();
+        let mut s = sys.system();
+        s.initialize(&mut world);
+        match s.run(&world) {
+            Err(EcsError::SystemError(err)) => assert_eq!(err.to_string(), "queue of u32 is empty"),
+            _ => unreachable!(),
+        }
+        world.get_mut::<Queue>().unwrap().0.push(1);
+        assert!(s.run(&world).is_ok());
+        world.get_mut::<Queue>().unwrap().0.push(2);
+        assert!(matches!(s.run(&world), Err(EcsError::AlreadyBorrowed)));
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn error_display() {
+        let errors = EcsError::DispatcherExecutionFailed(vec![
+            EcsError::NotInitialized,
+            EcsError::ConflictingAccess { type_name: "u32" },
+        ]);
+        assert_eq!(
+            errors.to_string(),
+            "2 system(s) failed: resource is not initialized; \
+            resource `u32` is borrowed more than once, at least once mutably"
+        );
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn error_context() {
+        fn load(path: &str) -> SystemResult {
+            ensure!(!path.is_empty(), "empty path");
+            Ok(())
+        }
+        let mut world = World::default();
+        let mut sys =
+            (|path: &String| load(path).map_err(|e| e.context("while loading the config")))
+                .system();
+        sys.initialize(&mut world);
+        let error = sys
+            .run(&world)
+            .map_err(|e| e.context(format!("in system `{}`", "setup")))
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "in system `setup`: while loading the config: empty path"
+        );
+        let source = error.source().unwrap();
+        assert_eq!(source.to_string(), "while loading the config: empty path");
+        assert_eq!(source.source().unwrap().to_string(), "empty path");
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn downcast_error() {
+        #[derive(Debug, PartialEq)]
+        struct OutOfFuel(u32);
+        impl std::fmt::Display for OutOfFuel {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "out of fuel after {} steps", self.0)
+            }
+        }
+        impl Error for OutOfFuel {}
+        fn ok(_a: &u32) -> SystemResult {
+            Ok(())
+        }
+        fn fail(_b: &mut u64) -> SystemResult {
+            system_error!(OutOfFuel(3))
+        }
+        let mut world = World::default();
+        let mut dispatch = DispatcherBuilder::new()
+            .add(ok)
+            .add(|_c: &u32| Err(EcsError::NotInitialized.context("while refueling")))
+            .add(fail)
+            .build(&mut world);
+        let error = dispatch.run(&mut world).unwrap_err();
+        assert!(matches!(error, EcsError::DispatcherExecutionFailed(_)));
+        assert_eq!(error.downcast_ref::<OutOfFuel>(), Some(&OutOfFuel(3)));
+        assert!(error.downcast_ref::<std::fmt::Error>().is_none());
+        let error = match error.downcast::<std::fmt::Error>() {
+            Err(error) => error,
+            Ok(_) => unreachable!(),
+        };
+        assert_eq!(error.downcast::<OutOfFuel>().unwrap(), OutOfFuel(3));
+
+        let error = EcsError::SystemError(Box::new(OutOfFuel(1))).context("while moving");
+        assert_eq!(error.downcast::<OutOfFuel>().unwrap(), OutOfFuel(1));
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn schedule_error_display() {
+        let mut world = World::default();
+        let sys = (|_: &u32, _: &mut u32| Ok(())).system();
+        let name = sys.name();
+        let error = DispatcherBuilder::new()
+            .add_system(sys)
+            .try_build(&mut world)
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "System `{}` cannot be borrowed at all, because it uses `u32` twice in its \
+                signature, at least once mutably.",
+                name
+            )
+        );
+        assert!(world.resource_names().next().is_none());
+
+        let error = DispatcherBuilder::new()
+            .add_shutdown_system((|| Ok(())).system().with_label("save"))
+            .extend(DispatcherBuilder::new().add_system((|| Ok(())).system().with_label("save")))
+            .try_build(&mut world)
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "The label `save` is used by systems of several merged builders."
+        );
+        let error = DispatcherBuilder::new()
+            .with_label("physics")
+            .extend(DispatcherBuilder::new().with_label("render"))
+            .try_build(&mut world)
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "The merged builders use different values of `label`."
+        );
+
+        world.initialize::<u32>();
+        std::mem::forget(world.get_mut::<u32>().unwrap());
+        let sys = (|_: &u32| Ok(())).system();
+        let name = sys.name();
+        match DispatcherBuilder::new()
+            .add_system(sys)
+            .try_build(&mut world)
+        {
+            Err(error @ ScheduleError::Unborrowable { .. }) => assert_eq!(
+                error.to_string(),
+                format!(
+                    "System `{}` cannot be borrowed at all: {}.",
+                    name,
+                    world.get::<u32>().err().unwrap()
+                )
+            ),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
     fn system_return_custom_error() {
         #[derive(Debug)]
         struct CustomError;
@@ -0,0 +1,143 @@
+use crate::*;
+use std::marker::PhantomData;
+
+/// A cached location of the resource `T` in a `World`, created by
+/// `World::handle`, which `World::get_via` and `World::get_mut_via` use to
+/// borrow it without looking it up by type.
+///
+/// Inserting or removing resources may move the other ones, so the handle
+/// is only used while the world hasn't changed structurally since it was
+/// created. After that, and on other worlds, those methods fall back to
+/// looking the resource up, and `World::handle` should be called again to
+/// get the fast path back.
+pub struct ResourceHandle<T> {
+    cell: *const ResourceCell,
+    world: WorldId,
+    generation: u64,
+    marker: PhantomData<fn() -> T>,
+}
+
+// Unsafe: the pointer is only dereferenced by the `World` it points into,
+// while it is still valid, as checked by its generation.
+unsafe impl<T> Send for ResourceHandle<T> {}
+unsafe impl<T> Sync for ResourceHandle<T> {}
+
+impl<T> Clone for ResourceHandle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for ResourceHandle<T> {}
+
+impl World {
+    /// Marks the storage as changed, invalidating the `ResourceHandle`s.
+    pub(crate) fn storage_changed(&mut self) {
+        self.generation += 1;
+    }
+
+    /// Returns a handle to the resource `T`, to borrow it repeatedly without
+    /// looking it up each time. See `ResourceHandle`.
+    /// Will return an error if the type is non initialized.
+    pub fn handle<T: Send + Sync + 'static>(&self) -> Result<ResourceHandle<T>, EcsError> {
+        let cell = self
+            .res
+            .get(&TypeId::of::<T>())
+            .ok_or(EcsError::NotInitialized)?;
+        Ok(ResourceHandle {
+            cell,
+            world: self.id(),
+            generation: self.generation,
+            marker: PhantomData,
+        })
+    }
+
+    fn cell_via<T: Send + Sync + 'static>(
+        &self,
+        handle: &ResourceHandle<T>,
+    ) -> Result<&ResourceCell, EcsError> {
+        if handle.world == self.id() && handle.generation == self.generation {
+            // Unsafe: the storage of this world didn't change since the
+            // handle was created, so the cell is still at the same place.
+            Ok(unsafe { &*handle.cell })
+        } else {
+            self.res
+                .get(&TypeId::of::<T>())
+                .ok_or(EcsError::NotInitialized)
+        }
+    }
+
+    /// Get an immutable reference to a resource using a handle, like `get`.
+    #[cfg_attr(feature = "debug_borrows", track_caller)]
+    pub fn get_via<T: Send + Sync + 'static>(
+        &self,
+        handle: &ResourceHandle<T>,
+    ) -> Result<Res<'_, T>, EcsError> {
+        #[cfg(feature = "debug_borrows")]
+        let caller = std::panic::Location::caller();
+        #[cfg(feature = "access_stats")]
+        self.count_access::<T>(false);
+        let i = self
+            .cell_via(handle)?
+            .try_borrow()
+            .map_err(|_| self.already_borrowed(&TypeId::of::<T>()))?;
+        #[cfg(feature = "debug_borrows")]
+        let i = self.record_borrower(TypeId::of::<T>(), i, caller);
+        Ok(Res::map(i, |j| j.downcast_ref::<T>().unwrap()))
+    }
+
+    /// Get a mutable reference to a resource using a handle, like `get_mut`.
+    #[cfg_attr(feature = "debug_borrows", track_caller)]
+    pub fn get_mut_via<T: Send + Sync + 'static>(
+        &self,
+        handle: &ResourceHandle<T>,
+    ) -> Result<ResMut<'_, T>, EcsError> {
+        #[cfg(feature = "debug_borrows")]
+        let caller = std::panic::Location::caller();
+        #[cfg(feature = "access_stats")]
+        self.count_access::<T>(true);
+        let i = self
+            .cell_via(handle)?
+            .try_borrow_mut()
+            .map_err(|_| self.already_borrowed(&TypeId::of::<T>()))?;
+        #[cfg(feature = "debug_borrows")]
+        let i = self.record_borrower(TypeId::of::<T>(), i, caller);
+        let i = self.bump_tick_mut(&TypeId::of::<T>(), i);
+        Ok(ResMut::map(i, |j| j.downcast_mut::<T>().unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::*;
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn resource_handle() {
+        let mut world = World::default().with_resource(1u32);
+        let handle = world.handle::<u32>().unwrap();
+        *world.get_mut_via(&handle).unwrap() += 1;
+        assert_eq!(*world.get_via(&handle).unwrap(), 2);
+        {
+            let _long_borrow = world.get_via(&handle).unwrap();
+            assert!(world.get_mut_via(&handle).is_err());
+        }
+
+        for i in 0..100u64 {
+            world.insert_if_absent(i as u8);
+            world.insert_if_absent(i);
+        }
+        assert_eq!(*world.get_via(&handle).unwrap(), 2);
+        let other = World::default().with_resource(7u32);
+        assert_eq!(*other.get_via(&handle).unwrap(), 7);
+
+        world.take_boxed(TypeId::of::<u32>()).unwrap();
+        assert!(matches!(
+            world.get_via(&handle),
+            Err(EcsError::NotInitialized)
+        ));
+        assert!(world.handle::<u32>().is_err());
+    }
+}
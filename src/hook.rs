@@ -0,0 +1,109 @@
+use crate::*;
+use std::sync::Arc;
+
+/// Tells whether a `SystemRunEvent` happens before or after the run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SystemRunPhase {
+    /// The system is about to run.
+    Before,
+    /// The system just ran.
+    After,
+}
+
+/// Describes a run of a system, passed to the hooks added with
+/// `DispatcherBuilder::on_system_run`.
+#[derive(Debug)]
+pub struct SystemRunEvent<'a> {
+    /// The name of the system, see `System::name`.
+    pub name: &'static str,
+    /// The label of the system, if it has one.
+    pub label: Option<&'static str>,
+    /// Whether the event happens before or after the run.
+    pub phase: SystemRunPhase,
+    /// What the system returned, only set in the `After` phase.
+    pub result: Option<&'a SystemResult>,
+}
+
+/// A hook added with `DispatcherBuilder::on_system_run`, shared by all the
+/// systems of the dispatcher.
+pub(crate) type SystemRunHook = Arc<dyn Fn(SystemRunEvent) + Send + Sync>;
+
+/// Makes the system call the hooks before and after each of its runs, in the
+/// order they were added.
+pub(crate) fn hook_system(sys: &mut System, hooks: &[SystemRunHook]) {
+    if hooks.is_empty() {
+        return;
+    }
+    let hooks = hooks.to_vec();
+    let (name, label) = (sys.name, sys.label);
+    let mut run_fn = std::mem::replace(&mut sys.run_fn, Box::new(|_| Ok(())));
+    sys.run_fn = Box::new(move |world: &World| {
+        for hook in &hooks {
+            hook(SystemRunEvent {
+                name,
+                label,
+                phase: SystemRunPhase::Before,
+                result: None,
+            });
+        }
+        let result = run_fn(world);
+        for hook in &hooks {
+            hook(SystemRunEvent {
+                name,
+                label,
+                phase: SystemRunPhase::After,
+                result: Some(&result),
+            });
+        }
+        result
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::*;
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn on_system_run() {
+        fn sys(_a: &u32) -> SystemResult {
+            Ok(())
+        }
+        fn failing(_b: &mut u64) -> SystemResult {
+            Err(EcsError::NotInitialized)
+        }
+        let before = Arc::new(AtomicUsize::new(0));
+        let after = Arc::new(AtomicUsize::new(0));
+        let failed = Arc::new(AtomicUsize::new(0));
+        let (b, a, e) = (before.clone(), after.clone(), failed.clone());
+        let mut world = World::default();
+        let mut dispatch = DispatcherBuilder::new()
+            .add(sys)
+            .add_system(failing.system().with_label("failing"))
+            .add_shutdown_system(sys.system())
+            .on_system_run(move |event| match event.phase {
+                SystemRunPhase::Before => {
+                    assert!(event.result.is_none());
+                    b.fetch_add(1, Ordering::SeqCst);
+                }
+                SystemRunPhase::After => {
+                    a.fetch_add(1, Ordering::SeqCst);
+                    if event.result.unwrap().is_err() {
+                        assert_eq!(event.label, Some("failing"));
+                        e.fetch_add(1, Ordering::SeqCst);
+                    }
+                }
+            })
+            .build(&mut world);
+        for _ in 0..3 {
+            assert!(dispatch.run(&mut world).is_err());
+        }
+        assert_eq!(before.load(Ordering::SeqCst), 6);
+        assert_eq!(after.load(Ordering::SeqCst), 6);
+        assert_eq!(failed.load(Ordering::SeqCst), 3);
+    }
+}
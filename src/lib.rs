@@ -3,28 +3,62 @@
 //! - Resources MAY use Mutex<Arc<T>> to be Send+Sync
 //! - Resources MUST be 'static
 
-use downcast_rs::{impl_downcast, Downcast};
-use std::any::TypeId;
 use atomic_refcell_try::*;
+use downcast_rs::{impl_downcast, Downcast};
+use std::any::{Any, TypeId};
 use std::collections::HashMap;
 use std::error::Error;
 use std::hash::{BuildHasherDefault, Hasher};
+use std::sync::Mutex;
 
 #[cfg(feature = "parallel")]
-use rayon::iter::{IntoParallelRefMutIterator, ParallelIterator};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
 #[cfg(feature = "profiler")]
 use thread_profiler::profile_scope;
 
+#[cfg(feature = "access_stats")]
+mod access_stats;
+mod change_tick;
+mod commands;
+#[cfg(feature = "debug_borrows")]
+mod debug_borrows;
 mod dispatcher;
 mod error;
+mod handle;
+mod hook;
+mod multi_borrow;
+pub mod prelude;
+mod profiling;
 mod resource;
+mod schedule;
+mod snapshot;
 mod system;
+#[cfg(feature = "sync_systems")]
+mod template;
+#[cfg(feature = "test_util")]
+pub mod test_util;
 mod typeid;
 mod world;
 
+#[cfg(feature = "access_stats")]
+use self::access_stats::*;
+use self::change_tick::*;
+pub use self::commands::*;
+#[cfg(feature = "debug_borrows")]
+pub use self::debug_borrows::*;
 pub use self::dispatcher::*;
 pub use self::error::*;
+pub use self::handle::*;
+pub use self::hook::*;
+pub use self::multi_borrow::*;
+pub use self::profiling::*;
 pub use self::resource::*;
+pub use self::schedule::*;
+pub use self::snapshot::*;
 pub use self::system::*;
+#[cfg(feature = "sync_systems")]
+pub use self::template::*;
 use self::typeid::*;
 pub use self::world::*;
+#[cfg(feature = "derive")]
+pub use world_dispatcher_derive::Resource;
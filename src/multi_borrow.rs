@@ -0,0 +1,364 @@
+use crate::*;
+
+/// A tuple of resource types that can be borrowed at once, immutably using
+/// `World::get_many_refs` or mutably using `World::get_disjoint_mut`.
+/// Implemented for tuples of up to 12 types.
+pub trait ResourceTuple {
+    /// The tuple of immutable borrows of the resources.
+    type Refs<'a>;
+    /// The tuple of mutable borrows of the resources.
+    type Muts<'a>;
+    /// Borrows all the resources immutably.
+    fn get_refs(world: &World) -> Result<Self::Refs<'_>, EcsError>;
+    /// Borrows all the resources mutably.
+    fn get_muts(world: &World) -> Result<Self::Muts<'_>, EcsError>;
+}
+
+macro_rules! impl_resource_tuple {
+    ($($id:ident,)*) => {
+        impl<$($id: Send + Sync + 'static,)*> ResourceTuple for ($($id,)*) {
+            type Refs<'a> = ($(Res<'a, $id>,)*);
+            type Muts<'a> = ($(ResMut<'a, $id>,)*);
+            fn get_refs(world: &World) -> Result<Self::Refs<'_>, EcsError> {
+                Ok(($(world.get_named::<$id>()?,)*))
+            }
+            fn get_muts(world: &World) -> Result<Self::Muts<'_>, EcsError> {
+                check_conflicts(&[], &[$(TypeId::of::<$id>()),*], &[$(World::type_name::<$id>()),*])?;
+                Ok(($(world.get_mut_named::<$id>()?,)*))
+            }
+        }
+    };
+}
+
+macro_rules! impl_resource_tuples {
+    () => {};
+    ($head:ident, $($idents:ident,)*) => {
+        impl_resource_tuple!($head, $($idents,)*);
+        impl_resource_tuples!($($idents,)*);
+    };
+}
+
+impl_resource_tuples!(A, B, C, D, E, G, H, I, J, K, L, M,);
+
+impl World {
+    /// Like `get`, but returns `EcsError::ResourceNotFound` when the type is not
+    /// initialized.
+    #[doc(hidden)]
+    #[cfg_attr(feature = "debug_borrows", track_caller)]
+    pub fn get_named<T: Send + Sync + 'static>(&self) -> Result<Res<'_, T>, EcsError> {
+        self.get::<T>().map_err(not_found::<T>)
+    }
+
+    /// Like `get_mut`, but returns `EcsError::ResourceNotFound` when the type is
+    /// not initialized.
+    #[doc(hidden)]
+    #[cfg_attr(feature = "debug_borrows", track_caller)]
+    pub fn get_mut_named<T: Send + Sync + 'static>(&self) -> Result<ResMut<'_, T>, EcsError> {
+        self.get_mut::<T>().map_err(not_found::<T>)
+    }
+
+    /// Get immutable references to multiple resources at once, as a tuple.
+    /// The same type can be borrowed more than once.
+    /// Will return an error if any type is:
+    /// - Non initialized, as `EcsError::ResourceNotFound`
+    /// - Already borrowed mutably
+    #[cfg_attr(feature = "debug_borrows", track_caller)]
+    pub fn get_many_refs<T: ResourceTuple>(&self) -> Result<T::Refs<'_>, EcsError> {
+        T::get_refs(self)
+    }
+
+    /// Get mutable references to multiple resources at once, as a tuple.
+    /// Will return an error if:
+    /// - The same type is requested more than once, as
+    ///   `EcsError::ConflictingAccess`
+    /// - Any type is non initialized, as `EcsError::ResourceNotFound`
+    /// - Any type is already borrowed immutably or mutably
+    #[cfg_attr(feature = "debug_borrows", track_caller)]
+    pub fn get_disjoint_mut<T: ResourceTuple>(&self) -> Result<T::Muts<'_>, EcsError> {
+        T::get_muts(self)
+    }
+
+    /// Get mutable references to two resources at once.
+    /// Will return an error if:
+    /// - `A` and `B` are the same type, as `EcsError::ConflictingAccess`
+    /// - Either type is non initialized, as `EcsError::ResourceNotFound`
+    /// - Either type is already borrowed immutably or mutably
+    #[cfg_attr(feature = "debug_borrows", track_caller)]
+    #[allow(clippy::type_complexity)]
+    pub fn get_two_mut<A: Send + Sync + 'static, B: Send + Sync + 'static>(
+        &self,
+    ) -> Result<(ResMut<'_, A>, ResMut<'_, B>), EcsError> {
+        self.get_disjoint_mut::<(A, B)>()
+    }
+
+    /// Get immutable references to the resources with the given type ids, all
+    /// held at once, for a consistent view of them that no system can write
+    /// to in the meantime. Each type id comes with the type name used in the
+    /// errors, usually `World::type_name`.
+    ///
+    /// The borrows are type-erased, like the ones held by the `Dispatcher`
+    /// while building its stages. Either all the resources are borrowed, or
+    /// none of them are: the borrows made before the failing one are
+    /// released. Will return an error if any type is:
+    /// - Non initialized, as `EcsError::ResourceNotFound`
+    /// - Already borrowed mutably, wrapped with `EcsError::context` naming the
+    ///   resource
+    pub fn borrow_all_shared(
+        &self,
+        types: &[(TypeId, &'static str)],
+    ) -> Result<Vec<Box<dyn RefLifetime + '_>>, EcsError> {
+        types
+            .iter()
+            .map(|&(id, type_name)| {
+                let res = self
+                    .res
+                    .get(&id)
+                    .ok_or(EcsError::ResourceNotFound { type_name })?;
+                let borrow = res.try_borrow().map_err(|_| {
+                    self.already_borrowed(&id)
+                        .context(format!("while borrowing `{}`", type_name))
+                })?;
+                Ok(Box::new(borrow) as Box<dyn RefLifetime>)
+            })
+            .collect()
+    }
+}
+
+/// Borrows multiple resources at once, immutably or mutably, as a tuple of
+/// guards, like the arguments of a system:
+/// `borrow!(world, &A, &mut B, &C)` returns a
+/// `Result<(Res<A>, ResMut<B>, Res<C>), EcsError>`.
+///
+/// The same type can be borrowed immutably more than once. Will return an
+/// error if:
+/// - A type is borrowed mutably more than once, or both mutably and
+///   immutably, as `EcsError::ConflictingAccess` naming the first type
+///   conflicting with a type before it, before borrowing anything
+/// - Any type is non initialized, as `EcsError::ResourceNotFound`
+/// - Any type is already borrowed in a conflicting way
+/// ```
+/// use world_dispatcher::*;
+///
+/// let mut world = World::default();
+/// world.initialize::<u32>();
+/// world.initialize::<u64>();
+/// {
+///     let (a, mut b, c) = borrow!(world, &u32, &mut u64, &u32).unwrap();
+///     *b += (*a + *c) as u64;
+/// }
+/// assert!(borrow!(world, &u32, &mut u32).is_err());
+/// ```
+#[macro_export]
+macro_rules! borrow {
+    ($world:expr, $($args:tt)+) => {
+        $crate::borrow!(@parse $world, [] [] $($args)+)
+    };
+    (@parse $world:expr, [$($check:tt)*] [$($guard:tt)*] &mut $t:ty $(, $($rest:tt)*)?) => {
+        $crate::borrow!(@parse $world,
+            [$($check)* (std::any::TypeId::of::<$t>(), true, $crate::World::type_name::<$t>()),]
+            [$($guard)* (get_mut_named $t)]
+            $($($rest)*)?)
+    };
+    (@parse $world:expr, [$($check:tt)*] [$($guard:tt)*] &$t:ty $(, $($rest:tt)*)?) => {
+        $crate::borrow!(@parse $world,
+            [$($check)* (std::any::TypeId::of::<$t>(), false, $crate::World::type_name::<$t>()),]
+            [$($guard)* (get_named $t)]
+            $($($rest)*)?)
+    };
+    (@parse $world:expr, [$($check:tt)*] [$(($get:ident $t:ty))*]) => {{
+        let world: &$crate::World = &$world;
+        $crate::check_borrow_conflicts(&[$($check)*])
+            .and_then(|()| Ok(($(world.$get::<$t>()?,)*)))
+    }};
+}
+
+/// Returns `EcsError::ConflictingAccess` for the first of the borrows,
+/// given as their type id, whether they are mutable and their type name,
+/// which conflicts with a borrow before it. Used by `borrow!`.
+#[doc(hidden)]
+pub fn check_borrow_conflicts(borrows: &[(TypeId, bool, &'static str)]) -> SystemResult {
+    for (i, (id, mutable, type_name)) in borrows.iter().enumerate() {
+        if borrows[..i]
+            .iter()
+            .any(|(other, other_mutable, _)| other == id && (*mutable || *other_mutable))
+        {
+            return Err(EcsError::ConflictingAccess { type_name });
+        }
+    }
+    Ok(())
+}
+
+/// Returns `EcsError::ConflictingAccess` if a type is borrowed mutably more
+/// than once, or both mutably and immutably. All the borrows that can
+/// be made at the same time, by a single call or a single system, go through
+/// this check.
+pub(crate) fn check_conflicts(
+    reads: &[TypeId],
+    writes: &[TypeId],
+    write_names: &[&'static str],
+) -> SystemResult {
+    for (i, w) in writes.iter().enumerate() {
+        if writes[..i].contains(w) || reads.contains(w) {
+            return Err(EcsError::ConflictingAccess {
+                type_name: write_names[i],
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Identifies the type in `EcsError::NotInitialized` errors.
+fn not_found<T>(e: EcsError) -> EcsError {
+    match e {
+        EcsError::NotInitialized => EcsError::ResourceNotFound {
+            type_name: World::type_name::<T>(),
+        },
+        e => e,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::*;
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn get_many_refs() {
+        let mut world = World::default();
+        world.initialize::<u32>();
+        world.initialize::<u64>();
+        *world.get_mut::<u64>().unwrap() = 2;
+        {
+            let (a, b, c) = world.get_many_refs::<(u32, u64, u32)>().unwrap();
+            assert_eq!((*a, *b, *c), (0, 2, 0));
+            let (one,) = world.get_many_refs::<(u64,)>().unwrap();
+            assert_eq!(*one, 2);
+            assert!(world.get_mut::<u64>().is_err());
+        }
+        match world.get_many_refs::<(u32, i8, u64)>() {
+            Err(EcsError::ResourceNotFound { type_name }) => assert_eq!(type_name, "i8"),
+            _ => unreachable!(),
+        }
+        let _long_borrow = world.get_mut::<u32>().unwrap();
+        assert!(world.get_many_refs::<(u64, u32)>().is_err());
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn get_disjoint_mut() {
+        let mut world = World::default();
+        world.initialize::<u32>();
+        world.initialize::<u64>();
+        world.initialize::<u8>();
+        {
+            let (mut a, mut b, mut c) = world.get_disjoint_mut::<(u32, u64, u8)>().unwrap();
+            *a = 1;
+            *b = 2;
+            *c = 3;
+            assert!(world.get::<u64>().is_err());
+        }
+        assert_eq!(*world.get::<u64>().unwrap(), 2);
+        match world.get_disjoint_mut::<(u32, u64, u32)>() {
+            Err(EcsError::ConflictingAccess { type_name }) => assert_eq!(type_name, "u32"),
+            _ => unreachable!(),
+        }
+        match world.get_disjoint_mut::<(u32, i8)>() {
+            Err(EcsError::ResourceNotFound { type_name }) => assert_eq!(type_name, "i8"),
+            _ => unreachable!(),
+        }
+        // The resources borrowed before the failure are released.
+        assert!(world.get_mut::<u32>().is_ok());
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn borrow_all_shared() {
+        let mut world = World::default();
+        world.initialize::<u32>();
+        world.initialize::<u64>();
+        {
+            let ids = [
+                (TypeId::of::<u32>(), World::type_name::<u32>()),
+                (TypeId::of::<u64>(), World::type_name::<u64>()),
+                (TypeId::of::<u32>(), World::type_name::<u32>()),
+            ];
+            let borrows = world.borrow_all_shared(&ids).unwrap();
+            assert_eq!(borrows.len(), 3);
+            assert!(world.get::<u64>().is_ok());
+            assert!(world.get_mut::<u32>().is_err());
+        }
+        let u32_id = (TypeId::of::<u32>(), World::type_name::<u32>());
+        match world.borrow_all_shared(&[u32_id, (TypeId::of::<i8>(), "i8")]) {
+            Err(e) => assert_eq!(e.to_string(), "resource `i8` is not in the world"),
+            _ => unreachable!(),
+        }
+        let _long_borrow = world.get_mut::<u64>().unwrap();
+        match world.borrow_all_shared(&[u32_id, (TypeId::of::<u64>(), "u64")]) {
+            Err(e) => assert_eq!(
+                e.to_string(),
+                format!(
+                    "while borrowing `u64`: {}",
+                    world.already_borrowed(&TypeId::of::<u64>())
+                )
+            ),
+            _ => unreachable!(),
+        }
+        // The resources borrowed before the failure are released.
+        assert!(world.get_mut::<u32>().is_ok());
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn borrow_macro() {
+        let mut world = World::default();
+        world.initialize::<u32>();
+        world.initialize::<u64>();
+        world.initialize::<u8>();
+        {
+            let (a, mut b, c, mut d) = borrow!(world, &u32, &mut u64, &u32, &mut u8).unwrap();
+            *b = 2;
+            *d = (*a + *c) as u8 + 1;
+            assert!(world.get::<u64>().is_err());
+            assert!(world.get::<u32>().is_ok());
+        }
+        assert_eq!(*world.get::<u8>().unwrap(), 1);
+        let (one,) = borrow!(world, &mut u64,).unwrap();
+        assert_eq!(*one, 2);
+        drop(one);
+        match borrow!(world, &u8, &u32, &mut u64, &mut u32, &mut u8) {
+            Err(EcsError::ConflictingAccess { type_name }) => assert_eq!(type_name, "u32"),
+            _ => unreachable!(),
+        }
+        match borrow!(world, &mut u8, &i8) {
+            Err(EcsError::ResourceNotFound { type_name }) => assert_eq!(type_name, "i8"),
+            _ => unreachable!(),
+        }
+        // The resources borrowed before the failure are released.
+        assert!(world.get_mut::<u8>().is_ok());
+        let _long_borrow = world.get::<u64>().unwrap();
+        assert!(borrow!(world, &u32, &mut u64).is_err());
+        assert!(borrow!(world, &u32, &u64).is_ok());
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn get_two_mut() {
+        let mut world = World::default();
+        world.initialize::<u32>();
+        world.initialize::<u64>();
+        {
+            let (mut a, mut b) = world.get_two_mut::<u32, u64>().unwrap();
+            *a += 1;
+            *b += 2;
+        }
+        assert_eq!(*world.get::<u32>().unwrap(), 1);
+        match world.get_two_mut::<u64, u64>() {
+            Err(EcsError::ConflictingAccess { type_name }) => assert_eq!(type_name, "u64"),
+            _ => unreachable!(),
+        }
+        let _long_borrow = world.get::<u64>().unwrap();
+        assert!(world.get_two_mut::<u32, u64>().is_err());
+    }
+}
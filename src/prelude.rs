@@ -0,0 +1,25 @@
+//! The common API, to be imported with `use world_dispatcher::prelude::*;`.
+//!
+//! ```
+//! use world_dispatcher::prelude::*;
+//!
+//! fn count(commands: &Commands, count: &mut u32) -> SystemResult {
+//!     *count += 1;
+//!     ensure!(*count < 10, "counted too far");
+//!     commands.push(|world: &mut World| world.initialize::<u64>());
+//!     Ok(())
+//! }
+//!
+//! let mut world = World::default();
+//! let mut dispatcher = DispatcherBuilder::new().add(count).build(&mut world);
+//! dispatcher.run(&mut world).unwrap();
+//! assert_eq!(*world.get::<u32>().unwrap(), 1);
+//! assert!(world.get::<u64>().is_ok());
+//! ```
+
+#[cfg(feature = "anyhow")]
+pub use crate::Anyhow;
+pub use crate::{
+    bind, borrow, ensure, system_error, then, Commands, Dispatcher, DispatcherBuilder, EcsError,
+    IntoSystem, RefLifetime, Res, ResMut, System, SystemResult, World,
+};
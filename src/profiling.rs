@@ -0,0 +1,147 @@
+use crate::*;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The timings of a system over all of its runs, see
+/// `Dispatcher::profiling_report`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SystemProfile {
+    /// The name of the system.
+    pub name: &'static str,
+    /// The number of runs.
+    pub runs: u32,
+    /// The total duration of all the runs.
+    pub total: Duration,
+    /// The duration of the shortest run.
+    pub min: Duration,
+    /// The duration of the longest run.
+    pub max: Duration,
+    /// The mean duration of the runs.
+    pub mean: Duration,
+    /// The duration of the last run.
+    pub last: Duration,
+}
+
+impl SystemProfile {
+    fn record(&mut self, duration: Duration) {
+        self.min = if self.runs == 0 {
+            duration
+        } else {
+            self.min.min(duration)
+        };
+        self.max = self.max.max(duration);
+        self.runs += 1;
+        self.total += duration;
+        self.mean = self.total / self.runs;
+        self.last = duration;
+    }
+}
+
+/// The profile of a system, shared between the system and its `Dispatcher`.
+pub(crate) type SharedProfile = Arc<Mutex<SystemProfile>>;
+
+/// Makes the system record the duration of each of its runs in the returned
+/// profile.
+pub(crate) fn profile_system(sys: &mut System) -> SharedProfile {
+    let profile = Arc::new(Mutex::new(SystemProfile {
+        name: sys.name(),
+        ..SystemProfile::default()
+    }));
+    let recorded = profile.clone();
+    let mut run_fn = std::mem::replace(&mut sys.run_fn, Box::new(|_| Ok(())));
+    sys.run_fn = Box::new(move |world: &World| {
+        let stopwatch = Stopwatch::start();
+        let result = run_fn(world);
+        recorded.lock().unwrap().record(stopwatch.elapsed());
+        result
+    });
+    profile
+}
+
+impl Dispatcher {
+    /// Returns the timings of each system over all the runs since the
+    /// dispatcher was built or `reset_profiling` was called, in execution
+    /// order, followed by the shutdown systems.
+    ///
+    /// Only available when built with `DispatcherBuilder::profiling`,
+    /// otherwise this is empty. Exclusive systems are not profiled.
+    pub fn profiling_report(&self) -> Vec<SystemProfile> {
+        self.profiles
+            .iter()
+            .map(|profile| profile.lock().unwrap().clone())
+            .collect()
+    }
+
+    /// Clears the timings returned by `profiling_report`.
+    pub fn reset_profiling(&mut self) {
+        for profile in &self.profiles {
+            let mut profile = profile.lock().unwrap();
+            *profile = SystemProfile {
+                name: profile.name,
+                ..SystemProfile::default()
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use std::time::Duration;
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::*;
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn record_profile() {
+        let mut profile = SystemProfile::default();
+        profile.record(Duration::from_millis(4));
+        profile.record(Duration::from_millis(2));
+        profile.record(Duration::from_millis(9));
+        assert_eq!(
+            profile,
+            SystemProfile {
+                name: "",
+                runs: 3,
+                total: Duration::from_millis(15),
+                min: Duration::from_millis(2),
+                max: Duration::from_millis(9),
+                mean: Duration::from_millis(5),
+                last: Duration::from_millis(9),
+            }
+        );
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn profiling_report() {
+        fn sys(_a: &u32) -> SystemResult {
+            Ok(())
+        }
+        let mut world = World::default();
+        let mut dispatch = DispatcherBuilder::new().add(sys).build(&mut world);
+        dispatch.run(&mut world).unwrap();
+        assert!(dispatch.profiling_report().is_empty());
+
+        let mut dispatch = DispatcherBuilder::new()
+            .add(sys)
+            .add(|_b: &mut u64| Ok(()))
+            .add_shutdown_system(sys.system())
+            .profiling()
+            .build(&mut world);
+        for _ in 0..3 {
+            dispatch.run(&mut world).unwrap();
+        }
+        let report = dispatch.profiling_report();
+        assert_eq!(report.len(), 3);
+        assert_eq!(report[0].name, sys.system().name());
+        assert_eq!(report[0].runs, 3);
+        assert_eq!(report[1].runs, 3);
+        assert!(report[0].min <= report[0].mean && report[0].mean <= report[0].max);
+        assert_eq!(report[2].runs, 0);
+        dispatch.reset_profiling();
+        dispatch.run(&mut world).unwrap();
+        assert_eq!(dispatch.profiling_report()[0].runs, 1);
+        assert_eq!(dispatch.profiling_report()[0].name, sys.system().name());
+    }
+}
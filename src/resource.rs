@@ -1,12 +1,378 @@
 use crate::*;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
 /// The type of `Resource`s.
 /// All types having a 'static lifetime automatically implement this.
 #[doc(hidden)]
-pub trait Resource: Send + Sync + 'static + Downcast {}
+pub trait Resource: Send + Sync + 'static + Downcast {
+    /// Returns the type name of the resource, as given by `World::type_name`.
+    fn resource_type_name(&self) -> &'static str {
+        World::type_name::<Self>()
+    }
+}
 impl<T> Resource for T where T: Send + Sync + 'static {}
 impl_downcast!(Resource);
 
+/// A resource holding a trait object `D`, such as a strategy chosen at
+/// runtime, keyed by the trait object type rather than by the type of its
+/// implementation.
+///
+/// It is empty by default, so that systems can take `&DynResource<D>` or
+/// `&mut DynResource<D>`, which deref to `D`. Insert an implementation with
+/// `World::insert_dyn`. `D` must be `Send + Sync`, for example by making
+/// them supertraits of the trait.
+pub struct DynResource<D: ?Sized>(Option<Box<D>>);
+
+impl<D: ?Sized> Default for DynResource<D> {
+    fn default() -> Self {
+        Self(None)
+    }
+}
+
+impl<D: ?Sized> DynResource<D> {
+    /// Creates a resource holding the given implementation.
+    pub fn new(value: Box<D>) -> Self {
+        Self(Some(value))
+    }
+
+    /// Replaces the implementation, returning the previous one if any.
+    pub fn set(&mut self, value: Box<D>) -> Option<Box<D>> {
+        self.0.replace(value)
+    }
+
+    /// Returns whether an implementation was inserted.
+    pub fn is_set(&self) -> bool {
+        self.0.is_some()
+    }
+}
+
+impl<D: ?Sized> std::ops::Deref for DynResource<D> {
+    type Target = D;
+    /// Panics if no implementation was inserted.
+    fn deref(&self) -> &D {
+        self.0
+            .as_deref()
+            .unwrap_or_else(|| dyn_resource_empty::<D>())
+    }
+}
+
+impl<D: ?Sized> std::ops::DerefMut for DynResource<D> {
+    /// Panics if no implementation was inserted.
+    fn deref_mut(&mut self) -> &mut D {
+        self.0
+            .as_deref_mut()
+            .unwrap_or_else(|| dyn_resource_empty::<D>())
+    }
+}
+
+fn dyn_resource_empty<D: ?Sized>() -> ! {
+    panic!(
+        "No implementation of `{}` was inserted, see `World::insert_dyn`.",
+        World::type_name::<D>()
+    )
+}
+
+/// A resource holding a value shared with other worlds or with code outside
+/// of the `World`, such as a value used by several dispatchers over the
+/// lifetime of the application, without moving it between them.
+///
+/// Systems take `&Shared<T>` and `lock` it to access the value, since the
+/// `World` only controls the borrows of the `Shared<T>` itself. The lock is
+/// held until the returned guard is dropped: systems sharing the value,
+/// including systems running in parallel in the same stage, wait for each
+/// other. Locking a `Shared` that is already locked on the same thread, or
+/// locking two of them in different orders in different threads, deadlocks.
+///
+/// It holds a fresh value by default. Insert a shared one with
+/// `World::insert_shared`.
+pub struct Shared<T>(std::sync::Arc<Mutex<T>>);
+
+impl<T: Default> Default for Shared<T> {
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+impl<T> Shared<T> {
+    /// Creates a resource sharing the given value.
+    pub fn new(value: std::sync::Arc<Mutex<T>>) -> Self {
+        Self(value)
+    }
+
+    /// Locks the value, blocking until no one else holds the lock.
+    ///
+    /// Panics if the lock is poisoned, because a thread panicked while
+    /// holding it.
+    pub fn lock(&self) -> std::sync::MutexGuard<'_, T> {
+        self.0.lock().unwrap()
+    }
+
+    /// Returns the shared value, to share it further.
+    pub fn value(&self) -> std::sync::Arc<Mutex<T>> {
+        self.0.clone()
+    }
+}
+
+/// The cell holding a resource in the `World`. Its `try_borrow` and
+/// `try_borrow_mut` return guards counting the borrows, see
+/// `World::borrow_count`, and it derefs to the underlying `AtomicRefCell`
+/// for everything else.
+pub(crate) struct ResourceCell {
+    cell: AtomicRefCell<Box<dyn Resource>>,
+    shared: AtomicUsize,
+    exclusive: AtomicBool,
+    /// The borrowers holding the guards that are still alive.
+    #[cfg(feature = "debug_borrows")]
+    pub(crate) borrowers: Mutex<Borrowers>,
+}
+
+impl ResourceCell {
+    pub(crate) fn new(value: Box<dyn Resource>) -> Self {
+        Self {
+            cell: AtomicRefCell::new(value),
+            shared: AtomicUsize::new(0),
+            exclusive: AtomicBool::new(false),
+            #[cfg(feature = "debug_borrows")]
+            borrowers: Mutex::default(),
+        }
+    }
+
+    pub(crate) fn into_inner(self) -> Box<dyn Resource> {
+        self.cell.into_inner()
+    }
+
+    /// Borrows the resource immutably, counting the borrow until the guard
+    /// is dropped.
+    #[inline]
+    pub(crate) fn try_borrow(&self) -> Result<Res<'_, Box<dyn Resource>>, BorrowError> {
+        let inner = self.cell.try_borrow()?;
+        self.shared.fetch_add(1, Ordering::Relaxed);
+        Ok(Res {
+            #[cfg(feature = "debug_borrows")]
+            borrower: None,
+            _count: SharedBorrow(&self.shared),
+            inner,
+        })
+    }
+
+    /// Borrows the resource mutably, marking it as such until the guard is
+    /// dropped.
+    #[inline]
+    pub(crate) fn try_borrow_mut(&self) -> Result<ResMut<'_, Box<dyn Resource>>, BorrowMutError> {
+        let inner = self.cell.try_borrow_mut()?;
+        self.exclusive.store(true, Ordering::Relaxed);
+        Ok(ResMut {
+            #[cfg(feature = "debug_borrows")]
+            borrower: None,
+            _count: ExclusiveBorrow(&self.exclusive),
+            inner,
+            detect: None,
+        })
+    }
+
+    /// Returns the number of guards returned by `try_borrow` alive, or
+    /// `usize::MAX` while the guard returned by `try_borrow_mut` is alive.
+    pub(crate) fn borrow_count(&self) -> usize {
+        if self.exclusive.load(Ordering::Relaxed) {
+            usize::MAX
+        } else {
+            self.shared.load(Ordering::Relaxed)
+        }
+    }
+}
+
+impl std::ops::Deref for ResourceCell {
+    type Target = AtomicRefCell<Box<dyn Resource>>;
+    fn deref(&self) -> &Self::Target {
+        &self.cell
+    }
+}
+
+impl std::ops::DerefMut for ResourceCell {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.cell
+    }
+}
+
+/// Counts a shared borrow until dropped.
+struct SharedBorrow<'a>(&'a AtomicUsize);
+
+impl Drop for SharedBorrow<'_> {
+    #[inline]
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Marks an exclusive borrow until dropped.
+struct ExclusiveBorrow<'a>(&'a AtomicBool);
+
+impl Drop for ExclusiveBorrow<'_> {
+    #[inline]
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+}
+
+/// An immutable borrow of a resource, returned by `World::get`.
+///
+/// It derefs to the resource, and forwards `AsRef`, `Borrow`, `Debug` and
+/// `Display` to it, so it can be used in comparisons, formatting and generic
+/// code directly. The resource is borrowed until it is dropped.
+pub struct Res<'a, T: ?Sized> {
+    // Declared first to be dropped first, so that a borrower is never
+    // reported once its borrow is released.
+    #[cfg(feature = "debug_borrows")]
+    borrower: Option<BorrowerEntry<'a>>,
+    // Dropped before `inner`, so that the borrow is never released while
+    // still counted.
+    _count: SharedBorrow<'a>,
+    inner: AtomicRef<'a, T>,
+}
+
+impl<'a, T: ?Sized> Res<'a, T> {
+    /// Makes a new `Res` for a part of the borrowed resource, such as one of
+    /// its fields.
+    pub fn map<U: ?Sized>(orig: Res<'a, T>, f: impl FnOnce(&T) -> &U) -> Res<'a, U> {
+        Res {
+            #[cfg(feature = "debug_borrows")]
+            borrower: orig.borrower,
+            _count: orig._count,
+            inner: AtomicRef::map(orig.inner, f),
+        }
+    }
+}
+
+/// A mutable borrow of a resource, returned by `World::get_mut`.
+///
+/// Like `Res`, it derefs to the resource and forwards `AsRef`, `Borrow`,
+/// `Debug` and `Display` to it, as well as `AsMut` and `BorrowMut`.
+pub struct ResMut<'a, T: ?Sized> {
+    // Dropped in the same order as the fields of `Res`.
+    #[cfg(feature = "debug_borrows")]
+    borrower: Option<BorrowerEntry<'a>>,
+    _count: ExclusiveBorrow<'a>,
+    inner: AtomicRefMut<'a, T>,
+    /// Dropped after the borrow is released, to compare the resource with
+    /// its copy, see `World::register_change_detect`.
+    detect: Option<DetectChange<'a>>,
+}
+
+impl<'a, T: ?Sized> ResMut<'a, T> {
+    /// Makes a new `ResMut` for a part of the borrowed resource, such as one
+    /// of its fields.
+    pub fn map<U: ?Sized>(orig: ResMut<'a, T>, f: impl FnOnce(&mut T) -> &mut U) -> ResMut<'a, U> {
+        ResMut {
+            #[cfg(feature = "debug_borrows")]
+            borrower: orig.borrower,
+            _count: orig._count,
+            inner: AtomicRefMut::map(orig.inner, f),
+            detect: orig.detect,
+        }
+    }
+
+    /// Compares the resource with its copy once the borrow is released.
+    pub(crate) fn detect_change(orig: &mut ResMut<'a, T>, detect: DetectChange<'a>) {
+        orig.detect = Some(detect);
+    }
+}
+
+#[cfg(feature = "debug_borrows")]
+impl<'a, T: ?Sized> RecordBorrower<'a> for Res<'a, T> {
+    fn with_borrower(mut self, entry: BorrowerEntry<'a>) -> Self {
+        self.borrower = Some(entry);
+        self
+    }
+}
+
+#[cfg(feature = "debug_borrows")]
+impl<'a, T: ?Sized> RecordBorrower<'a> for ResMut<'a, T> {
+    fn with_borrower(mut self, entry: BorrowerEntry<'a>) -> Self {
+        self.borrower = Some(entry);
+        self
+    }
+}
+
+impl<T: ?Sized> std::ops::Deref for Res<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T: ?Sized> std::ops::Deref for ResMut<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T: ?Sized> std::ops::DerefMut for ResMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+}
+
+impl<T: ?Sized> AsRef<T> for Res<'_, T> {
+    fn as_ref(&self) -> &T {
+        self
+    }
+}
+
+impl<T: ?Sized> AsRef<T> for ResMut<'_, T> {
+    fn as_ref(&self) -> &T {
+        self
+    }
+}
+
+impl<T: ?Sized> AsMut<T> for ResMut<'_, T> {
+    fn as_mut(&mut self) -> &mut T {
+        self
+    }
+}
+
+impl<T: ?Sized> std::borrow::Borrow<T> for Res<'_, T> {
+    fn borrow(&self) -> &T {
+        self
+    }
+}
+
+impl<T: ?Sized> std::borrow::Borrow<T> for ResMut<'_, T> {
+    fn borrow(&self) -> &T {
+        self
+    }
+}
+
+impl<T: ?Sized> std::borrow::BorrowMut<T> for ResMut<'_, T> {
+    fn borrow_mut(&mut self) -> &mut T {
+        self
+    }
+}
+
+impl<T: ?Sized + std::fmt::Debug> std::fmt::Debug for Res<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+impl<T: ?Sized + std::fmt::Debug> std::fmt::Debug for ResMut<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+impl<T: ?Sized + std::fmt::Display> std::fmt::Display for Res<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+impl<T: ?Sized + std::fmt::Display> std::fmt::Display for ResMut<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
 /// Hacky trait to extend the lifetime of a Ref<'a, T>, which is used
 /// internally in the `Dispatcher`'s logic.
 /// Import this if you get errors where RefLifetime is not implemented for
@@ -14,5 +380,7 @@ impl_downcast!(Resource);
 pub trait RefLifetime {}
 impl<'a, T> RefLifetime for AtomicRef<'a, T> {}
 impl<'a, T> RefLifetime for AtomicRefMut<'a, T> {}
-impl<'a, T> RefLifetime for &'a T {}
-impl<'a, T> RefLifetime for &'a mut T {}
+impl<T: ?Sized> RefLifetime for Res<'_, T> {}
+impl<T: ?Sized> RefLifetime for ResMut<'_, T> {}
+impl<T> RefLifetime for &T {}
+impl<T> RefLifetime for &mut T {}
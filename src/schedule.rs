@@ -0,0 +1,162 @@
+use crate::*;
+use std::marker::PhantomData;
+
+/// A marker type for a phase of a `Schedule`.
+///
+/// Phases are usually unit structs:
+/// ```
+/// use world_dispatcher::*;
+/// struct Update;
+/// impl Phase for Update {}
+/// ```
+pub trait Phase: 'static {}
+
+/// A list of phases, implemented for tuples of up to 8 `Phase`s.
+pub trait Phases {
+    /// The number of phases in the list.
+    const COUNT: usize;
+}
+
+/// The index of a phase in a list of phases. Only used to disambiguate the
+/// `HasPhase` implementations.
+pub struct PhaseIndex<const N: usize>;
+
+/// Implemented by lists of phases containing the phase `P`.
+///
+/// `I` is inferred automatically and can be ignored.
+pub trait HasPhase<P: Phase, I>: Phases {
+    /// The position of `P` in the list of phases.
+    const INDEX: usize;
+}
+
+macro_rules! impl_has_phase {
+    ([$($all:ident)*] $phase:ident $index:tt) => {
+        impl<$($all: Phase,)*> HasPhase<$phase, PhaseIndex<$index>> for ($($all,)*) {
+            const INDEX: usize = $index;
+        }
+    };
+}
+
+macro_rules! impl_phases {
+    ($count:tt $all:tt; $($phase:ident $index:tt,)*) => {
+        impl_phases_count!($count $all);
+        $(impl_has_phase!($all $phase $index);)*
+    };
+}
+
+macro_rules! impl_phases_count {
+    ($count:tt [$($all:ident)*]) => {
+        impl<$($all: Phase,)*> Phases for ($($all,)*) {
+            const COUNT: usize = $count;
+        }
+    };
+}
+
+impl_phases!(1 [A]; A 0,);
+impl_phases!(2 [A B]; A 0, B 1,);
+impl_phases!(3 [A B C]; A 0, B 1, C 2,);
+impl_phases!(4 [A B C D]; A 0, B 1, C 2, D 3,);
+impl_phases!(5 [A B C D E]; A 0, B 1, C 2, D 3, E 4,);
+impl_phases!(6 [A B C D E G]; A 0, B 1, C 2, D 3, E 4, G 5,);
+impl_phases!(7 [A B C D E G H]; A 0, B 1, C 2, D 3, E 4, G 5, H 6,);
+impl_phases!(8 [A B C D E G H I]; A 0, B 1, C 2, D 3, E 4, G 5, H 6, I 7,);
+
+/// A builder accumulating systems into a fixed list of phases, known at
+/// compile time.
+///
+/// Adding a system to a phase that isn't part of `P` doesn't compile:
+/// ```compile_fail
+/// use world_dispatcher::*;
+/// struct Update;
+/// impl Phase for Update {}
+/// struct Render;
+/// impl Phase for Render {}
+///
+/// let schedule = Schedule::<(Update,)>::new().add_to_phase(Render, || Ok(()));
+/// ```
+///
+/// Once all systems are added, the phases are merged in order into a
+/// `DispatcherBuilder`, which can be extended like any other.
+pub struct Schedule<P: Phases> {
+    phases: Vec<DispatcherBuilder>,
+    _phases: PhantomData<fn() -> P>,
+}
+
+impl<P: Phases> Default for Schedule<P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<P: Phases> Schedule<P> {
+    /// Creates a new `Schedule` with empty phases.
+    pub fn new() -> Self {
+        Self {
+            phases: (0..P::COUNT).map(|_| DispatcherBuilder::new()).collect(),
+            _phases: PhantomData,
+        }
+    }
+
+    /// Adds a function implementing `IntoSystem` to the given phase.
+    pub fn add_to_phase<Ph: Phase, I, R, F: IntoSystem<R>>(self, phase: Ph, into_system: F) -> Self
+    where
+        P: HasPhase<Ph, I>,
+    {
+        self.add_system_to_phase(phase, into_system.system())
+    }
+
+    /// Adds a `System` to the given phase.
+    pub fn add_system_to_phase<Ph: Phase, I>(mut self, _phase: Ph, system: System) -> Self
+    where
+        P: HasPhase<Ph, I>,
+    {
+        self.phases[<P as HasPhase<Ph, I>>::INDEX]
+            .systems
+            .push(system);
+        self
+    }
+
+    /// Merges the phases, in order, into a `DispatcherBuilder`.
+    pub fn into_builder(self) -> DispatcherBuilder {
+        self.phases
+            .into_iter()
+            .fold(DispatcherBuilder::new(), DispatcherBuilder::extend)
+    }
+
+    /// Builds a `Dispatcher` running the phases in order.
+    pub fn build(self, world: &mut World) -> Dispatcher {
+        self.into_builder().build(world)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::*;
+
+    struct Update;
+    impl Phase for Update {}
+    struct Render;
+    impl Phase for Render {}
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn phases_run_in_order() {
+        #[derive(Default)]
+        struct Log(Vec<&'static str>);
+        let mut world = World::default();
+        let mut dispatch = Schedule::<(Update, Render)>::new()
+            .add_to_phase(Render, |log: &mut Log| {
+                log.0.push("render");
+                Ok(())
+            })
+            .add_to_phase(Update, |log: &mut Log| {
+                log.0.push("update");
+                Ok(())
+            })
+            .build(&mut world);
+        dispatch.run_seq(&world).unwrap();
+        assert_eq!(world.get::<Log>().unwrap().0, vec!["update", "render"]);
+    }
+}
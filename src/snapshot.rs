@@ -0,0 +1,124 @@
+use crate::*;
+
+/// A type-erased clone of a resource, registered with `World::register_clone`.
+pub(crate) type CloneFn = fn(&dyn Resource) -> Box<dyn Resource>;
+
+pub(crate) fn clone_resource<T: Clone + Send + Sync + 'static>(
+    res: &dyn Resource,
+) -> Box<dyn Resource> {
+    Box::new(res.downcast_ref::<T>().unwrap().clone())
+}
+
+/// Copies of the resources registered with `World::register_clone`, created
+/// by `World::snapshot_typed`.
+#[derive(Default)]
+pub struct TypedSnapshot {
+    values: Vec<(TypeId, CloneFn, Box<dyn Resource>)>,
+}
+
+impl TypedSnapshot {
+    /// Returns the number of resources in the snapshot.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns whether the snapshot has no resources.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+impl Clone for TypedSnapshot {
+    fn clone(&self) -> Self {
+        Self {
+            values: self
+                .values
+                .iter()
+                .map(|(id, clone, value)| (*id, *clone, clone(&**value)))
+                .collect(),
+        }
+    }
+}
+
+impl World {
+    /// Registers the resource type `T` as cloneable, so that it is saved by
+    /// `snapshot_typed`. The resource doesn't need to be in the world yet.
+    pub fn register_clone<T: Clone + Send + Sync + 'static>(&mut self) {
+        self.clones
+            .insert(TypeId::of::<T>(), clone_resource::<T> as CloneFn);
+    }
+
+    /// Clones each resource registered with `register_clone` that is in the
+    /// world, to put them back later using `restore_typed`.
+    ///
+    /// Will return `EcsError::AlreadyBorrowed` if one of them is borrowed
+    /// mutably.
+    pub fn snapshot_typed(&self) -> Result<TypedSnapshot, EcsError> {
+        let mut values = vec![];
+        for (id, clone) in self.clones.iter() {
+            if let Some(res) = self.res.get(id) {
+                let res = res.try_borrow().map_err(|_| self.already_borrowed(id))?;
+                values.push((*id, *clone, clone(&**res)));
+            }
+        }
+        Ok(TypedSnapshot { values })
+    }
+
+    /// Writes back the resources of a snapshot created by `snapshot_typed`,
+    /// replacing their current values, or inserting them if they were
+    /// removed since. The other resources are left untouched.
+    pub fn restore_typed(&mut self, snapshot: TypedSnapshot) {
+        for (id, _, value) in snapshot.values {
+            match self.res.get_mut(&id) {
+                Some(res) => {
+                    *res.get_mut() = value;
+                    self.forget_change(&id);
+                    self.bump_tick(&id);
+                }
+                None => {
+                    let _ = self.insert_boxed_checked(value);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::*;
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn snapshot_restore() {
+        #[derive(Clone, Default)]
+        struct Score(u32);
+        let mut world = World::default();
+        world.register_clone::<Score>();
+        world.register_clone::<String>();
+        world.initialize::<Score>();
+        world.initialize::<u64>();
+        world.get_mut::<Score>().unwrap().0 = 3;
+        let snapshot = world.snapshot_typed().unwrap();
+        assert_eq!(snapshot.len(), 1);
+
+        world.get_mut::<Score>().unwrap().0 = 10;
+        *world.get_mut::<u64>().unwrap() = 4;
+        world.restore_typed(snapshot.clone());
+        assert_eq!(world.get::<Score>().unwrap().0, 3);
+        assert_eq!(*world.get::<u64>().unwrap(), 4);
+
+        world.take_boxed(TypeId::of::<Score>()).unwrap();
+        world.restore_typed(snapshot);
+        assert_eq!(world.get::<Score>().unwrap().0, 3);
+
+        let _long_borrow = world.get_mut::<Score>().unwrap();
+        match world.snapshot_typed() {
+            Err(EcsError::AlreadyBorrowed) => {}
+            #[cfg(feature = "debug_borrows")]
+            Err(EcsError::AlreadyBorrowedBy(_)) => {}
+            _ => unreachable!(),
+        }
+    }
+}
@@ -1,16 +1,150 @@
 use crate::*;
+use std::sync::Arc;
 
 /// Struct used to run a system function using the world.
 /// This struct is also used internally by the `Dispatcher` to create a coherent
 /// execution sequence.
-pub struct System {
-    pub initialize: Box<dyn Fn(&mut World) + Send>,
-    pub lock: Box<dyn Fn(*const World, *mut Vec<Box<dyn RefLifetime>>) -> SystemResult + Send>,
-    pub run_fn: Box<dyn FnMut(&World) -> SystemResult + Send>,
+///
+/// Systems run by a `Dispatcher` return nothing on success, but systems
+/// run manually can return a value of type `Out`.
+pub struct System<Out = ()> {
+    pub initialize: InitializeFn,
+    pub lock: LockFn,
+    pub run_fn: RunFn<Out>,
+    /// Creates a new `run_fn` sharing the system function, which lets
+    /// `DispatcherBuilder::build_template` use the system in several
+    /// dispatchers. Only set with the `sync_systems` feature.
+    pub run_factory: Option<RunFactory<Out>>,
     pub name: &'static str,
+    pub access: Access,
+    /// A label identifying the system, see `System::with_label`.
+    pub label: Option<&'static str>,
+    /// Whether the system must run on the thread calling the `Dispatcher`,
+    /// see `DispatcherBuilder::add_main_thread_system`.
+    pub main_thread: bool,
 }
 
-impl System {
+/// The thread-safety bounds required from system functions.
+///
+/// This is `Send`, and also `Sync` with the `sync_systems` feature, which
+/// makes `System` and `Dispatcher` `Sync` so they can be shared behind an
+/// `Arc`. The tradeoff is that functions capturing `!Sync` state, such as a
+/// `Cell`, can't be turned into systems anymore.
+#[cfg(not(feature = "sync_systems"))]
+pub trait SystemBound: Send {}
+#[cfg(not(feature = "sync_systems"))]
+impl<T: Send> SystemBound for T {}
+/// The thread-safety bounds required from system functions.
+///
+/// This is `Send`, and also `Sync` with the `sync_systems` feature, which
+/// makes `System` and `Dispatcher` `Sync` so they can be shared behind an
+/// `Arc`. The tradeoff is that functions capturing `!Sync` state, such as a
+/// `Cell`, can't be turned into systems anymore.
+#[cfg(feature = "sync_systems")]
+pub trait SystemBound: Send + Sync {}
+#[cfg(feature = "sync_systems")]
+impl<T: Send + Sync> SystemBound for T {}
+
+/// The shared function initializing the resources of a `System`.
+pub type InitializeFn = Arc<dyn Fn(&mut World) + Send + Sync>;
+/// The shared function locking the resources of a `System`.
+pub type LockFn =
+    Arc<dyn Fn(*const World, *mut Vec<Box<dyn RefLifetime>>) -> SystemResult + Send + Sync>;
+/// The shared function creating the `run_fn` of a `System`.
+pub type RunFactory<Out> = Arc<dyn Fn() -> RunFn<Out> + Send + Sync>;
+
+#[cfg(not(feature = "sync_systems"))]
+mod boxed {
+    use crate::*;
+    /// The boxed function running a `System`.
+    pub type RunFn<Out> = Box<dyn FnMut(&World) -> SystemResult<Out> + Send>;
+    /// The boxed function of an exclusive system.
+    pub(crate) type ExclusiveFn = Box<dyn FnMut(&mut World) -> SystemResult + Send>;
+    /// The boxed run criteria of a `Dispatcher`.
+    pub(crate) type RunCriteriaFn = Box<dyn Fn(&World) -> bool + Send>;
+}
+#[cfg(feature = "sync_systems")]
+mod boxed {
+    use crate::*;
+    /// The boxed function running a `System`.
+    pub type RunFn<Out> = Box<dyn FnMut(&World) -> SystemResult<Out> + Send + Sync>;
+    /// The boxed function of an exclusive system.
+    pub(crate) type ExclusiveFn = Box<dyn FnMut(&mut World) -> SystemResult + Send + Sync>;
+    /// The shared run criteria of a `Dispatcher`, which can be cloned into
+    /// the dispatchers created from a `DispatcherTemplate`.
+    pub(crate) type RunCriteriaFn = std::sync::Arc<dyn Fn(&World) -> bool + Send + Sync>;
+}
+pub use boxed::*;
+
+/// Describes the resources a `System` borrows.
+///
+/// A `&World` argument is listed as a read of `World`.
+#[derive(Clone, Debug, Default)]
+pub struct Access {
+    /// The types borrowed immutably.
+    pub reads: Vec<TypeId>,
+    /// The types borrowed mutably.
+    pub writes: Vec<TypeId>,
+    /// The type names of `reads`, in the same order.
+    pub read_names: Vec<&'static str>,
+    /// The type names of `writes`, in the same order.
+    pub write_names: Vec<&'static str>,
+}
+
+impl Access {
+    /// Returns `EcsError::ConflictingAccess` if the same type is borrowed
+    /// mutably more than once, or both mutably and immutably.
+    pub fn check_conflicts(&self) -> SystemResult {
+        check_conflicts(&self.reads, &self.writes, &self.write_names)
+    }
+
+    /// Returns whether the two accesses can't be held at the same time,
+    /// because they borrow the same resource and at least one of them
+    /// borrows it mutably.
+    ///
+    /// Reading `World` conflicts with writing any resource.
+    pub fn conflicts_with(&self, other: &Access) -> bool {
+        let reads_world = |access: &Access| access.reads.contains(&TypeId::of::<World>());
+        (reads_world(self) && !other.writes.is_empty())
+            || (reads_world(other) && !self.writes.is_empty())
+            || self
+                .writes
+                .iter()
+                .any(|w| other.reads.contains(w) || other.writes.contains(w))
+            || other.writes.iter().any(|w| self.reads.contains(w))
+    }
+
+    /// Returns the resources borrowed by either access, listing the types
+    /// borrowed mutably by one of them only in `writes`.
+    fn union(&self, other: &Access) -> Access {
+        let mut union = Access::default();
+        for (id, name) in self
+            .writes
+            .iter()
+            .zip(&self.write_names)
+            .chain(other.writes.iter().zip(&other.write_names))
+        {
+            if !union.writes.contains(id) {
+                union.writes.push(*id);
+                union.write_names.push(name);
+            }
+        }
+        for (id, name) in self
+            .reads
+            .iter()
+            .zip(&self.read_names)
+            .chain(other.reads.iter().zip(&other.read_names))
+        {
+            if !union.reads.contains(id) && !union.writes.contains(id) {
+                union.reads.push(*id);
+                union.read_names.push(name);
+            }
+        }
+        union
+    }
+}
+
+impl<Out: 'static> System<Out> {
     /// Initializes the resources required to run this system inside of the
     /// provided `World`, if those resources don't already exist.
     ///
@@ -20,10 +154,119 @@ impl System {
         (self.initialize)(world)
     }
     /// Runs the system's function using the provided `World`'s resources.
-    pub fn run(&mut self, world: &World) -> SystemResult {
+    pub fn run(&mut self, world: &World) -> SystemResult<Out> {
+        #[cfg(feature = "debug_borrows")]
+        let _current = CurrentSystem::enter(self.name);
         (self.run_fn)(world)
     }
 
+    /// Makes the system run again, up to `retries` more times, when it
+    /// returns an error. It stops as soon as a run succeeds, and only the
+    /// last error is returned if all of the runs fail.
+    ///
+    /// Retries are not appropriate for systems whose side effects
+    /// aren't idempotent, since a failed run may have been partially applied.
+    pub fn with_retries(self, retries: usize) -> Self {
+        self.map_run_fn(move |mut run_fn| {
+            Box::new(move |world: &World| {
+                let mut result = run_fn(world);
+                for _ in 0..retries {
+                    if result.is_ok() {
+                        break;
+                    }
+                    result = run_fn(world);
+                }
+                result
+            })
+        })
+    }
+
+    /// Wraps the `run_fn` of the system, along with the ones created by its
+    /// `run_factory`.
+    fn map_run_fn<T: 'static>(
+        self,
+        wrap: impl Fn(RunFn<Out>) -> RunFn<T> + Copy + Send + Sync + 'static,
+    ) -> System<T> {
+        System {
+            initialize: self.initialize,
+            lock: self.lock,
+            run_fn: wrap(self.run_fn),
+            run_factory: self.run_factory.map(|factory| {
+                let factory: RunFactory<T> = Arc::new(move || wrap(factory()));
+                factory
+            }),
+            name: self.name,
+            access: self.access,
+            label: self.label,
+            main_thread: self.main_thread,
+        }
+    }
+
+    /// Labels the system.
+    ///
+    /// Since closures have no stable identity, labels are used to recognize
+    /// a system, for example to avoid adding it twice with
+    /// `DispatcherBuilder::add_system_unique`.
+    pub fn with_label(mut self, label: &'static str) -> Self {
+        self.label = Some(label);
+        self
+    }
+
+    /// Returns the label of this system, if it has one.
+    pub fn label(&self) -> Option<&'static str> {
+        self.label
+    }
+
+    /// Checks that every resource in the `Access` of this system is in the
+    /// provided `World`, without running or borrowing anything.
+    ///
+    /// Returns `EcsError::ResourceNotFound` for the first missing resource.
+    /// Unlike `Dispatcher::dry_run`, resources borrowed elsewhere are not
+    /// reported.
+    pub fn validate_against(&self, world: &World) -> SystemResult {
+        let access = &self.access;
+        access
+            .reads
+            .iter()
+            .zip(access.read_names.iter())
+            .chain(access.writes.iter().zip(access.write_names.iter()))
+            .find(|(id, _)| **id != TypeId::of::<World>() && !world.res.contains_key(id))
+            .map_or(Ok(()), |(_, type_name)| {
+                Err(EcsError::ResourceNotFound { type_name })
+            })
+    }
+
+    /// Returns the resources borrowed by this system.
+    pub fn access(&self) -> &Access {
+        &self.access
+    }
+
+    /// Returns the types this system borrows immutably.
+    pub fn reads(&self) -> &[TypeId] {
+        &self.access.reads
+    }
+
+    /// Returns the types this system borrows mutably.
+    pub fn writes(&self) -> &[TypeId] {
+        &self.access.writes
+    }
+
+    /// Returns the number of resources this system borrows, immutably or
+    /// mutably. A `&World` argument counts as one.
+    pub fn arity(&self) -> usize {
+        self.access.reads.len() + self.access.writes.len()
+    }
+
+    /// Returns the type names of the types this system borrows immutably.
+    pub fn reads_names(&self) -> &[&'static str] {
+        &self.access.read_names
+    }
+
+    /// Returns the type names of the types this system borrows mutably.
+    pub fn writes_names(&self) -> &[&'static str] {
+        &self.access.write_names
+    }
+
     /// Returns the underlying type name of the system. This is not guranteed to
     /// be stable or human-readable, but can be used for diagnostics.
     pub fn name(&self) -> &'static str {
@@ -31,47 +274,594 @@ impl System {
     }
 }
 
+/// Tells whether a system created with `System::repeating` should run again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SystemControl {
+    /// The system is done for this dispatch.
+    Continue,
+    /// The system has more work: run it again immediately, before the
+    /// dispatcher moves on.
+    Repeat,
+}
+
+impl System<SystemControl> {
+    /// Converts a system returning a `SystemControl` into a system that the
+    /// dispatcher can run, which runs it again each time it returns
+    /// `SystemControl::Repeat`.
+    ///
+    /// The system runs up to `max_repeats` more times in each dispatch. If it
+    /// still asks to be repeated after that, it returns
+    /// `EcsError::MaxIterationsExceeded(max_repeats)`. Errors stop the
+    /// repetition and are returned as they are.
+    /// ```
+    /// use world_dispatcher::*;
+    ///
+    /// fn halve(n: &mut u32) -> SystemResult<SystemControl> {
+    ///     *n /= 2;
+    ///     Ok(if *n > 1 { SystemControl::Repeat } else { SystemControl::Continue })
+    /// }
+    ///
+    /// let mut world = World::default();
+    /// world.initialize::<u32>();
+    /// *world.get_mut::<u32>().unwrap() = 100;
+    /// let mut dispatcher = DispatcherBuilder::new()
+    ///     .add_system(halve.system().repeating(10))
+    ///     .build(&mut world);
+    /// dispatcher.run_seq(&world).unwrap();
+    /// assert_eq!(*world.get::<u32>().unwrap(), 1);
+    /// ```
+    pub fn repeating(self, max_repeats: usize) -> System {
+        self.map_run_fn(move |mut run_fn| {
+            Box::new(move |world: &World| {
+                for _ in 0..max_repeats {
+                    if run_fn(world)? == SystemControl::Continue {
+                        return Ok(());
+                    }
+                }
+                match run_fn(world)? {
+                    SystemControl::Continue => Ok(()),
+                    SystemControl::Repeat => Err(EcsError::MaxIterationsExceeded(max_repeats)),
+                }
+            })
+        })
+    }
+}
+
+/// Combines two systems into one, which runs `first` and then runs `second`
+/// only if `first` succeeded. The error of `first` is returned as is when it
+/// fails, which lets a system guard the systems depending on it.
+///
+/// The combined system borrows the resources of both systems, for as long
+/// as both of them run.
+/// ```
+/// use world_dispatcher::*;
+///
+/// fn guard(enabled: &bool) -> SystemResult {
+///     ensure!(*enabled, "disabled");
+///     Ok(())
+/// }
+/// fn count(n: &mut u32) -> SystemResult {
+///     *n += 1;
+///     Ok(())
+/// }
+///
+/// let mut world = World::default();
+/// let mut system = then(guard.system(), count.system());
+/// system.initialize(&mut world);
+/// assert!(system.run(&world).is_err());
+/// assert_eq!(*world.get::<u32>().unwrap(), 0);
+/// ```
+pub fn then<Out: 'static>(first: System, second: System<Out>) -> System<Out> {
+    let access = first.access.union(&second.access);
+    let locked_access = access.clone();
+    let (init_first, init_second) = (first.initialize, second.initialize);
+    let run_factory = match (first.run_factory, second.run_factory) {
+        (Some(first), Some(second)) => {
+            let factory: RunFactory<Out> = Arc::new(move || then_run_fn(first(), second()));
+            Some(factory)
+        }
+        _ => None,
+    };
+    System {
+        initialize: Arc::new(move |world: &mut World| {
+            init_first(world);
+            init_second(world);
+        }),
+        lock: Arc::new(
+            move |world: *const World, locked: *mut Vec<Box<dyn RefLifetime>>| {
+                // Unsafe: see the `lock` of the systems created by `IntoSystem`.
+                unsafe { lock_access(&locked_access, &*world, &mut *locked) }
+            },
+        ),
+        run_fn: then_run_fn(first.run_fn, second.run_fn),
+        run_factory,
+        name: first.name,
+        access,
+        label: None,
+        main_thread: first.main_thread || second.main_thread,
+    }
+}
+
+fn then_run_fn<Out: 'static>(mut first: RunFn<()>, mut second: RunFn<Out>) -> RunFn<Out> {
+    Box::new(move |world: &World| {
+        first(world)?;
+        second(world)
+    })
+}
+
 /// Converts a function into a `System`. It is required to execute a function
 /// automatically from `World`'s resources.
 /// This trait is automatically implemented for functions taking 12 arguments (22 if using the
 /// `big_systems` feature)
 /// or less where:
 /// - All arguments are immutable or mutable references.
-/// - All immutable references are placed *before* all mutable references.
+/// - All immutable references are placed *before* all mutable references,
+///   unless using the `flexible_ordering` feature, which accepts any order for
+///   functions taking 8 arguments or less, at the cost of compile time.
 /// - All arguments implement `Default`.
-/// - Does not use the same type twice.
-/// - Returns a `SystemResult` (usually just `Ok(())`).
-pub trait IntoSystem<R> {
-    fn system(self) -> System;
+/// - Does not use the same type twice, unless all uses are immutable references.
+/// - Returns a `SystemResult` (usually just `Ok(())`), or a `SystemResult<Out>`
+///   to produce a value.
+///
+/// A `&World` argument is passed the `World` itself instead of a resource, to
+/// access resources dynamically. Systems taking it cannot take any `&mut`
+/// argument, and never run in parallel with systems writing to resources.
+///
+/// Functions breaking these rules don't compile. When passed to a function
+/// requiring `IntoSystem`, such as `DispatcherBuilder::add`, the error lists
+/// these rules. With the method syntax, `f.system()`, the compiler only says
+/// that there is no method named `system`:
+#[cfg_attr(not(feature = "flexible_ordering"), doc = "```compile_fail")]
+#[cfg_attr(feature = "flexible_ordering", doc = "```")]
+/// use world_dispatcher::*;
+/// // `&mut` arguments must come after `&` arguments, unless using
+/// // `flexible_ordering`.
+/// let _ = (|_a: &mut u32, _b: &u64| Ok(())).system();
+/// ```
+/// ```compile_fail
+/// use world_dispatcher::*;
+/// // Too many arguments, even with `big_systems`.
+/// fn sys(
+///     _: &u8, _: &u8, _: &u8, _: &u8, _: &u8, _: &u8, _: &u8, _: &u8, _: &u8, _: &u8, _: &u8, _: &u8,
+///     _: &u8, _: &u8, _: &u8, _: &u8, _: &u8, _: &u8, _: &u8, _: &u8, _: &u8, _: &u8, _: &u8,
+/// ) -> SystemResult {
+///     Ok(())
+/// }
+/// let _ = sys.system();
+/// ```
+/// ```compile_fail
+/// use world_dispatcher::*;
+/// struct NoDefault;
+/// let _ = (|_a: &NoDefault| Ok(())).system();
+/// ```
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be converted into a `System`",
+    label = "not a valid system",
+    note = "systems take at most 12 arguments, or 22 with the `big_systems` feature",
+    note = "all `&` arguments must come before all `&mut` arguments",
+    note = "all argument types must implement `Default`, use `Option<T>` for types that don't",
+    note = "systems must return a `SystemResult`"
+)]
+pub trait IntoSystem<R, Out = ()> {
+    fn system(self) -> System<Out>;
+}
+
+/// Wraps a function returning an `anyhow::Result` so it can be converted into
+/// a `System`, using `?` on any error type.
+///
+/// Errors are returned as `EcsError::SystemError`. Only available with the
+/// `anyhow` feature.
+/// ```
+/// use world_dispatcher::*;
+///
+/// #[derive(Default)]
+/// struct Config(String);
+///
+/// fn load(config: &mut Config) -> anyhow::Result<()> {
+///     config.0 = std::fs::read_to_string("config.toml")?;
+///     Ok(())
+/// }
+///
+/// let mut world = World::default();
+/// let mut dispatcher = DispatcherBuilder::new().add(Anyhow(load)).build(&mut world);
+/// assert!(dispatcher.run(&mut world).is_err());
+/// ```
+#[cfg(feature = "anyhow")]
+pub struct Anyhow<F>(pub F);
+
+/// An instance bound to one of its methods, converted into a `System` calling
+/// the method on the instance. Created by `bind`.
+pub struct Bound<S, F>(S, F);
+
+/// Binds an instance to one of its methods taking `&self` followed by the
+/// arguments of a system, so the method can be converted into a `System`
+/// owning the instance. The system is `Send` if the instance is.
+/// ```
+/// use world_dispatcher::*;
+///
+/// struct Scorer {
+///     bonus: u32,
+/// }
+///
+/// impl Scorer {
+///     fn update(&self, score: &mut u32) -> SystemResult {
+///         *score += self.bonus;
+///         Ok(())
+///     }
+/// }
+///
+/// let mut world = World::default();
+/// let mut dispatcher = DispatcherBuilder::new()
+///     .add(bind(Scorer { bonus: 3 }, Scorer::update))
+///     .build(&mut world);
+/// dispatcher.run(&mut world).unwrap();
+/// assert_eq!(*world.get::<u32>().unwrap(), 3);
+/// ```
+pub fn bind<S, F>(instance: S, method: F) -> Bound<S, F> {
+    Bound(instance, method)
+}
+
+/// A tuple of values implementing `IntoSystem`, converted into systems all at
+/// once by `DispatcherBuilder::add_systems`.
+/// Implemented for tuples of up to 12 elements.
+pub trait IntoSystemTuple<R> {
+    /// Converts each element into a `System`, in order.
+    fn systems(self) -> Vec<System>;
+}
+
+macro_rules! impl_into_system_tuple {
+    ($($f:ident $r:ident,)*) => {
+        impl<$($r, $f: IntoSystem<$r>,)*> IntoSystemTuple<($($r,)*)> for ($($f,)*) {
+            #[allow(non_snake_case)]
+            fn systems(self) -> Vec<System> {
+                let ($($f,)*) = self;
+                vec![$($f.system()),*]
+            }
+        }
+    };
+}
+
+macro_rules! impl_into_system_tuples {
+    () => {};
+    ($f:ident $r:ident, $($fs:ident $rs:ident,)*) => {
+        impl_into_system_tuple!($f $r, $($fs $rs,)*);
+        impl_into_system_tuples!($($fs $rs,)*);
+    };
+}
+
+impl_into_system_tuples!(A RA, B RB, C RC, D RD, E RE, G RG, H RH, I RI, J RJ, K RK, L RL, M RM,);
+
+/// A shared system argument, fetched from the `World`.
+pub(crate) enum Fetch<'a, T> {
+    Resource(Res<'a, T>),
+    World(&'a T),
+}
+
+impl<T> std::ops::Deref for Fetch<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        match self {
+            Fetch::Resource(r) => r,
+            Fetch::World(w) => w,
+        }
+    }
+}
+
+fn is_world<T: 'static>() -> bool {
+    TypeId::of::<T>() == TypeId::of::<World>()
+}
+
+/// Fetches a shared system argument. `&World` arguments get the world itself.
+pub(crate) fn fetch<T: Send + Sync + 'static>(world: &World) -> Result<Fetch<'_, T>, EcsError> {
+    if is_world::<T>() {
+        // Safe: T is World.
+        return Ok(Fetch::World(unsafe {
+            &*(world as *const World as *const T)
+        }));
+    }
+    world.get::<T>().map(Fetch::Resource)
+}
+
+/// Initializes the resource of a system argument, unless it is the `World`.
+pub(crate) fn initialize_arg<T: Default + Send + Sync + 'static>(world: &mut World) {
+    if !is_world::<T>() {
+        world.initialize::<T>();
+    }
+}
+
+/// Borrows a shared system argument to find conflicts between systems.
+/// `&World` arguments borrow every resource, since they can access any of
+/// them.
+///
+/// The resources are borrowed from their cells directly rather than with
+/// `World::get`, so that locking doesn't count as an access.
+pub(crate) fn lock_arg<'a, T: Send + Sync + 'static>(
+    world: &'a World,
+    locked: &mut Vec<Box<dyn RefLifetime + 'a>>,
+) -> SystemResult {
+    if is_world::<T>() {
+        for (id, res) in world.res.iter() {
+            push_lock(
+                world,
+                locked,
+                res.try_borrow().map_err(|_| world.already_borrowed(id))?,
+            );
+        }
+    } else {
+        let id = TypeId::of::<T>();
+        let cell = world.res.get(&id).ok_or(EcsError::NotInitialized)?;
+        push_lock(
+            world,
+            locked,
+            cell.try_borrow().map_err(|_| world.already_borrowed(&id))?,
+        );
+    }
+    Ok(())
+}
+
+/// Borrows all the resources of an `Access`, by their `TypeId`. A `World`
+/// read borrows every resource that isn't written.
+pub(crate) fn lock_access<'a>(
+    access: &Access,
+    world: &'a World,
+    locked: &mut Vec<Box<dyn RefLifetime + 'a>>,
+) -> SystemResult {
+    let cell = |id: &TypeId| world.res.get(id).ok_or(EcsError::NotInitialized);
+    for id in &access.reads {
+        if *id == TypeId::of::<World>() {
+            for (id, res) in world.res.iter() {
+                if !access.writes.contains(id) {
+                    push_lock(
+                        world,
+                        locked,
+                        res.try_borrow().map_err(|_| world.already_borrowed(id))?,
+                    );
+                }
+            }
+        } else {
+            push_lock(
+                world,
+                locked,
+                cell(id)?
+                    .try_borrow()
+                    .map_err(|_| world.already_borrowed(id))?,
+            );
+        }
+    }
+    for id in &access.writes {
+        push_lock(
+            world,
+            locked,
+            cell(id)?
+                .try_borrow_mut()
+                .map_err(|_| world.already_borrowed(id))?,
+        );
+    }
+    Ok(())
+}
+
+/// Borrows a mutable system argument to find conflicts between systems.
+///
+/// Like `lock_arg`, this borrows the resource without going through
+/// `World::get_mut`, so it isn't marked as changed.
+pub(crate) fn lock_arg_mut<'a, T: Send + Sync + 'static>(
+    world: &'a World,
+    locked: &mut Vec<Box<dyn RefLifetime + 'a>>,
+) -> SystemResult {
+    let id = TypeId::of::<T>();
+    let cell = world.res.get(&id).ok_or(EcsError::NotInitialized)?;
+    push_lock(
+        world,
+        locked,
+        cell.try_borrow_mut()
+            .map_err(|_| world.already_borrowed(&id))?,
+    );
+    Ok(())
+}
+
+/// A guard extended by `System::lock`, counted by
+/// `World::outstanding_borrows` until it is dropped.
+#[cfg(debug_assertions)]
+struct CountedGuard<'a, G> {
+    _guard: G,
+    count: &'a std::sync::atomic::AtomicUsize,
+}
+
+#[cfg(debug_assertions)]
+impl<G> RefLifetime for CountedGuard<'_, G> {}
+
+#[cfg(debug_assertions)]
+impl<G> Drop for CountedGuard<'_, G> {
+    fn drop(&mut self) {
+        self.count.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
+    }
+}
+
+/// Adds a guard to the ones held by `System::lock`, counting it in debug
+/// builds.
+fn push_lock<'a>(
+    world: &'a World,
+    locked: &mut Vec<Box<dyn RefLifetime + 'a>>,
+    guard: impl RefLifetime + 'a,
+) {
+    #[cfg(debug_assertions)]
+    {
+        world
+            .lock_guards
+            .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        locked.push(Box::new(CountedGuard {
+            _guard: guard,
+            count: &world.lock_guards,
+        }));
+    }
+    #[cfg(not(debug_assertions))]
+    {
+        let _ = world;
+        locked.push(Box::new(guard));
+    }
+}
+
+/// Panics if the arguments of a system are invalid in a way that the type
+/// system can't check.
+pub(crate) fn check_args(reads: &[TypeId], writes: &[TypeId]) {
+    assert!(
+        !writes.contains(&TypeId::of::<World>()),
+        "Systems cannot take `&mut World` as an argument, use an exclusive system instead."
+    );
+    assert!(
+        writes.is_empty() || !reads.contains(&TypeId::of::<World>()),
+        "Systems taking `&World` as an argument cannot take any `&mut` argument."
+    );
+}
+
+/// Creates the `run_fn` of a system calling `f` with `run`, along with its
+/// `run_factory` when `f` can be shared between threads.
+#[cfg(feature = "sync_systems")]
+pub(crate) fn share_run_fn<F, Out, R>(f: F, run: R) -> (RunFn<Out>, Option<RunFactory<Out>>)
+where
+    F: SystemBound + 'static,
+    R: Fn(&F, &World) -> SystemResult<Out> + Copy + SystemBound + 'static,
+{
+    let f = Arc::new(f);
+    let factory: RunFactory<Out> = Arc::new(move || {
+        let f = f.clone();
+        Box::new(move |world: &World| run(&f, world))
+    });
+    (factory(), Some(factory))
+}
+
+/// Creates the `run_fn` of a system calling `f` with `run`.
+#[cfg(not(feature = "sync_systems"))]
+pub(crate) fn share_run_fn<F, Out, R>(f: F, run: R) -> (RunFn<Out>, Option<RunFactory<Out>>)
+where
+    F: SystemBound + 'static,
+    R: Fn(&F, &World) -> SystemResult<Out> + Copy + SystemBound + 'static,
+{
+    (Box::new(move |world: &World| run(&f, world)), None)
+}
+
+/// The arguments of a system function, as a tuple of references, such as
+/// `(&A, &mut B)`.
+///
+/// This holds the parts of a system which only depend on its arguments, so
+/// that they are generated once for each list of arguments rather than in each
+/// `IntoSystem` implementation, to keep the (slow) build of those short.
+pub(crate) trait SystemArgs {
+    /// Returns the resources borrowed by the arguments.
+    fn access() -> Access;
+    /// Initializes the resources of the arguments.
+    fn initialize(world: &mut World);
+    /// Borrows the resources of the arguments, see `System::lock`.
+    fn lock(world: *const World, locked: *mut Vec<Box<dyn RefLifetime>>) -> SystemResult;
+}
+
+/// Creates a system calling `f` with `run`, taking the arguments `Args`.
+pub(crate) fn new_system<Args, F, Out, R>(f: F, run: R, name: &'static str) -> System<Out>
+where
+    Args: SystemArgs,
+    F: SystemBound + 'static,
+    R: Fn(&F, &World) -> SystemResult<Out> + Copy + SystemBound + 'static,
+{
+    let access = Args::access();
+    check_args(&access.reads, &access.writes);
+    let (run_fn, run_factory) = share_run_fn(f, run);
+    System {
+        initialize: Arc::new(Args::initialize as fn(&mut World)),
+        lock: lock_fn(&access, Args::lock),
+        run_fn,
+        run_factory,
+        name,
+        access,
+        label: None,
+        main_thread: false,
+    }
+}
+
+/// Creates the `lock` of a system, which fails if the system borrows the same
+/// resource twice, at least once mutably, and otherwise calls `lock_args`.
+fn lock_fn(
+    access: &Access,
+    lock_args: fn(*const World, *mut Vec<Box<dyn RefLifetime>>) -> SystemResult,
+) -> LockFn {
+    let conflicting = access.check_conflicts().is_err().then(|| access.clone());
+    Arc::new(
+        move |world: *const World, locked: *mut Vec<Box<dyn RefLifetime>>| {
+            if let Some(access) = &conflicting {
+                return access.check_conflicts();
+            }
+            lock_args(world, locked)
+        },
+    )
 }
 
 macro_rules! impl_system {
     ($($id:ident,)* $(&mut $idmut:ident,)*) => {
-        impl<$($id: Send + Sync,)* $($idmut: Send + Sync,)* F> IntoSystem<($(&$id,)* $(&mut $idmut,)*)> for F
+        impl<$($id: Send + Sync + Default + 'static,)* $($idmut: Send + Sync + Default + 'static,)*> SystemArgs for ($(&$id,)* $(&mut $idmut,)*) {
+            fn access() -> Access {
+                Access {
+                    reads: vec![$(TypeId::of::<$id>()),*],
+                    writes: vec![$(TypeId::of::<$idmut>()),*],
+                    read_names: vec![$(World::type_name::<$id>()),*],
+                    write_names: vec![$(World::type_name::<$idmut>()),*],
+                }
+            }
+            fn initialize(_world: &mut World) {
+                $(initialize_arg::<$id>(_world);)*
+                $(_world.initialize::<$idmut>();)*
+            }
+            fn lock(world: *const World, locked: *mut Vec<Box<dyn RefLifetime>>) -> SystemResult {
+                // Unsafe: used to extend the lifetime because we need to store the
+                // reference of a value that is inside a RefCell to keep the counter
+                // incremented.
+                let (_world, _locked) = unsafe { (&*world, &mut *locked) };
+                $(lock_arg::<$id>(_world, _locked)?;)*
+                $(lock_arg_mut::<$idmut>(_world, _locked)?;)*
+                Ok(())
+            }
+        }
+
+        impl<$($id: Send + Sync,)* $($idmut: Send + Sync,)* Out, F> IntoSystem<($(&$id,)* $(&mut $idmut,)*), Out> for F
         where
             $($id: Default+'static,)*
             $($idmut: Default+'static,)*
-            F: Fn($(&$id,)* $(&mut $idmut,)*) -> SystemResult + 'static + Send,
+            F: Fn($(&$id,)* $(&mut $idmut,)*) -> SystemResult<Out> + 'static + SystemBound,
         {
-            fn system(self) -> System {
-                System {
-                    initialize: Box::new(|_world: &mut World| {
-                        $(_world.initialize::<$id>();)*
-                        $(_world.initialize::<$idmut>();)*
-                    }),
-                    lock: Box::new(|_world: *const World, _locked: *mut Vec<Box<dyn RefLifetime>>| {
-                        // Unsafe: used to extend the lifetime because we need to store the
-                        // reference of a value that is inside a RefCell to keep the counter
-                        // incremented.
-                        $(unsafe {(&mut *_locked).push(Box::new((*_world).get::<$id>()?))};)*
-                        $(unsafe {(&mut *_locked).push(Box::new((*_world).get_mut::<$idmut>()?))};)*
-                        Ok(())
-                    }),
-                    run_fn: Box::new(move |_world: &World| {
-                        self($(&*_world.get::<$id>()?,)* $(&mut *_world.get_mut::<$idmut>()?),*)
-                    }),
-                    name: std::any::type_name::<F>()
-                }
+            fn system(self) -> System<Out> {
+                let run = |f: &F, _world: &World| {
+                    f($(&*fetch::<$id>(_world)?,)* $(&mut *_world.get_mut::<$idmut>()?),*)
+                };
+                new_system::<($(&$id,)* $(&mut $idmut,)*), _, _, _>(self, run, World::type_name::<F>())
+            }
+        }
+
+        #[cfg(feature = "anyhow")]
+        impl<$($id: Send + Sync,)* $($idmut: Send + Sync,)* Out, F> IntoSystem<($(&$id,)* $(&mut $idmut,)*), Out> for Anyhow<F>
+        where
+            $($id: Default+'static,)*
+            $($idmut: Default+'static,)*
+            F: Fn($(&$id,)* $(&mut $idmut,)*) -> anyhow::Result<Out> + 'static + SystemBound,
+        {
+            fn system(self) -> System<Out> {
+                let run = |f: &F, _world: &World| {
+                    f($(&*fetch::<$id>(_world)?,)* $(&mut *_world.get_mut::<$idmut>()?),*)
+                        .map_err(EcsError::from)
+                };
+                new_system::<($(&$id,)* $(&mut $idmut,)*), _, _, _>(self.0, run, World::type_name::<F>())
+            }
+        }
+
+        impl<$($id: Send + Sync,)* $($idmut: Send + Sync,)* Out, Instance, F> IntoSystem<($(&$id,)* $(&mut $idmut,)*), Out> for Bound<Instance, F>
+        where
+            $($id: Default+'static,)*
+            $($idmut: Default+'static,)*
+            Instance: 'static + SystemBound,
+            F: Fn(&Instance, $(&$id,)* $(&mut $idmut,)*) -> SystemResult<Out> + 'static + SystemBound,
+        {
+            fn system(self) -> System<Out> {
+                let run = |bound: &Bound<Instance, F>, _world: &World| {
+                    (bound.1)(&bound.0, $(&*fetch::<$id>(_world)?,)* $(&mut *_world.get_mut::<$idmut>()?),*)
+                };
+                new_system::<($(&$id,)* $(&mut $idmut,)*), _, _, _>(self, run, World::type_name::<F>())
             }
         }
     }
@@ -105,23 +895,183 @@ impl_systems!(A, B, C, D, E, G, H, I, J, K, L, M,);
 //impl_systems!(A, B, C, D, E, G, H, I, J, K, L, M, O, P, Q, R,);
 // 26, 17s build time
 //impl_systems!(A, B, C, D, E, G, H, I, J, K, L, M, O, P, Q, R, S, T, U, V, W, X, Y, Z, AA,);
-// 22, 10s build time
+// 22, 10s build time (14s before `SystemArgs` was split out of the impls)
 impl_systems!(A, B, C, D, E, G, H, I, J, K, L, M, O, P, Q, R, S, T, U, V, W,);
 
+/// The type of a system argument for `impl_system_flexible`: `r` for reads,
+/// `w` for writes.
+#[cfg(feature = "flexible_ordering")]
+macro_rules! flex_ty {
+    (r $t:ident) => {
+        &$t
+    };
+    (w $t:ident) => {
+        &mut $t
+    };
+}
+
+#[cfg(feature = "flexible_ordering")]
+macro_rules! flex_arg {
+    (r $t:ident, $world:ident) => {
+        &*fetch::<$t>($world)?
+    };
+    (w $t:ident, $world:ident) => {
+        &mut *$world.get_mut::<$t>()?
+    };
+}
+
+/// Adds a system argument to the `Access` of the system.
+#[cfg(feature = "flexible_ordering")]
+macro_rules! flex_access {
+    (r $t:ident, $access:ident) => {
+        $access.reads.push(TypeId::of::<$t>());
+        $access.read_names.push(World::type_name::<$t>());
+    };
+    (w $t:ident, $access:ident) => {
+        $access.writes.push(TypeId::of::<$t>());
+        $access.write_names.push(World::type_name::<$t>());
+    };
+}
+
+#[cfg(feature = "flexible_ordering")]
+macro_rules! flex_initialize {
+    (r $t:ident, $world:ident) => {
+        initialize_arg::<$t>($world)
+    };
+    (w $t:ident, $world:ident) => {
+        $world.initialize::<$t>()
+    };
+}
+
+#[cfg(feature = "flexible_ordering")]
+macro_rules! flex_lock {
+    (r $t:ident, $world:expr, $locked:expr) => {
+        lock_arg::<$t>($world, $locked)?
+    };
+    (w $t:ident, $world:expr, $locked:expr) => {
+        lock_arg_mut::<$t>($world, $locked)?
+    };
+}
+
+/// Like `impl_system`, for arguments given in any order, as `[r A]` for reads
+/// and `[w A]` for writes.
+#[cfg(feature = "flexible_ordering")]
+macro_rules! impl_system_flexible {
+    ($([$k:ident $t:ident])*) => {
+        impl<$($t: Send + Sync + Default + 'static,)*> SystemArgs for ($(flex_ty!($k $t),)*) {
+            fn access() -> Access {
+                let mut access = Access::default();
+                $(flex_access!($k $t, access);)*
+                access
+            }
+            fn initialize(_world: &mut World) {
+                $(flex_initialize!($k $t, _world);)*
+            }
+            fn lock(world: *const World, locked: *mut Vec<Box<dyn RefLifetime>>) -> SystemResult {
+                // Unsafe: see `impl_system`.
+                let (_world, _locked) = unsafe { (&*world, &mut *locked) };
+                $(flex_lock!($k $t, _world, _locked);)*
+                Ok(())
+            }
+        }
+
+        impl<$($t: Send + Sync + Default + 'static,)* Out, F> IntoSystem<($(flex_ty!($k $t),)*), Out> for F
+        where
+            F: Fn($(flex_ty!($k $t),)*) -> SystemResult<Out> + 'static + SystemBound,
+        {
+            fn system(self) -> System<Out> {
+                let run = |f: &F, _world: &World| f($(flex_arg!($k $t, _world),)*);
+                new_system::<($(flex_ty!($k $t),)*), _, _, _>(self, run, World::type_name::<F>())
+            }
+        }
+
+        #[cfg(feature = "anyhow")]
+        impl<$($t: Send + Sync + Default + 'static,)* Out, F> IntoSystem<($(flex_ty!($k $t),)*), Out> for Anyhow<F>
+        where
+            F: Fn($(flex_ty!($k $t),)*) -> anyhow::Result<Out> + 'static + SystemBound,
+        {
+            fn system(self) -> System<Out> {
+                let run = |f: &F, _world: &World| {
+                    f($(flex_arg!($k $t, _world),)*).map_err(EcsError::from)
+                };
+                new_system::<($(flex_ty!($k $t),)*), _, _, _>(self.0, run, World::type_name::<F>())
+            }
+        }
+
+        impl<$($t: Send + Sync + Default + 'static,)* Out, Instance, F> IntoSystem<($(flex_ty!($k $t),)*), Out> for Bound<Instance, F>
+        where
+            Instance: 'static + SystemBound,
+            F: Fn(&Instance, $(flex_ty!($k $t),)*) -> SystemResult<Out> + 'static + SystemBound,
+        {
+            fn system(self) -> System<Out> {
+                let run = |bound: &Bound<Instance, F>, _world: &World| {
+                    (bound.1)(&bound.0, $(flex_arg!($k $t, _world),)*)
+                };
+                new_system::<($(flex_ty!($k $t),)*), _, _, _>(self, run, World::type_name::<F>())
+            }
+        }
+    }
+}
+
+/// Generates `impl_system_flexible` for every ordering of the arguments in
+/// which a read comes after a write, the other ones being covered by
+/// `impl_system`. `@sorted` means no write was seen yet, `@writing` that no
+/// read was seen after a write yet.
+#[cfg(feature = "flexible_ordering")]
+macro_rules! impl_systems_flexible {
+    (@sorted [$($acc:tt)*] []) => {};
+    (@writing [$($acc:tt)*] []) => {};
+    (@unsorted [$($acc:tt)*] []) => {
+        impl_system_flexible!($($acc)*);
+    };
+    (@sorted [$($acc:tt)*] [$t:ident $($rest:ident)*]) => {
+        impl_systems_flexible!(@sorted [$($acc)* [r $t]] [$($rest)*]);
+        impl_systems_flexible!(@writing [$($acc)* [w $t]] [$($rest)*]);
+    };
+    (@writing [$($acc:tt)*] [$t:ident $($rest:ident)*]) => {
+        impl_systems_flexible!(@unsorted [$($acc)* [r $t]] [$($rest)*]);
+        impl_systems_flexible!(@writing [$($acc)* [w $t]] [$($rest)*]);
+    };
+    (@unsorted [$($acc:tt)*] [$t:ident $($rest:ident)*]) => {
+        impl_systems_flexible!(@unsorted [$($acc)* [r $t]] [$($rest)*]);
+        impl_systems_flexible!(@unsorted [$($acc)* [w $t]] [$($rest)*]);
+    };
+    ($($t:ident)*) => {
+        impl_systems_flexible!(@sorted [] [$($t)*]);
+    };
+}
+
+#[cfg(feature = "flexible_ordering")]
+impl_systems_flexible!(A B);
+#[cfg(feature = "flexible_ordering")]
+impl_systems_flexible!(A B C);
+#[cfg(feature = "flexible_ordering")]
+impl_systems_flexible!(A B C D);
+#[cfg(feature = "flexible_ordering")]
+impl_systems_flexible!(A B C D E);
+#[cfg(feature = "flexible_ordering")]
+impl_systems_flexible!(A B C D E G);
+#[cfg(feature = "flexible_ordering")]
+impl_systems_flexible!(A B C D E G H);
+#[cfg(feature = "flexible_ordering")]
+impl_systems_flexible!(A B C D E G H I);
+
 #[cfg(test)]
 mod tests {
     use crate::*;
+    #[cfg(target_arch = "wasm32")]
     use wasm_bindgen_test::*;
 
     #[test]
-    #[wasm_bindgen_test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
     fn convert_system() {
         let _ = generic::<u32>.system();
         fn tmp(_var1: &u32, _var2: &u64, _var3: &mut i32, _var4: &mut i64) -> SystemResult {
             Ok(())
         }
-        // Technically reusing the same type is incorrect and causes a runtime panic.
+        // Technically reusing the same type mutably is incorrect and causes a runtime panic.
         // However, there doesn't seem to be a clean way to handle type inequality in generics.
+        #[allow(clippy::too_many_arguments)]
         fn tmp2(
             _var1: &u32,
             _var2: &u64,
@@ -143,7 +1093,7 @@ mod tests {
     }
 
     #[test]
-    #[wasm_bindgen_test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
     fn system_is_send() {
         let x = 6;
         send(
@@ -157,6 +1107,23 @@ mod tests {
         send(sys.system());
     }
 
+    #[cfg(feature = "sync_systems")]
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn system_is_sync() {
+        fn sync<T: Sync>(_t: T) {}
+        sync(sys.system());
+        let dispatcher = std::sync::Arc::new(
+            DispatcherBuilder::new()
+                .add(sys)
+                .build(&mut World::default()),
+        );
+        let shared = dispatcher.clone();
+        std::thread::spawn(move || shared.iter_stages().count())
+            .join()
+            .unwrap();
+    }
+
     fn sys(_var1: &u32) -> SystemResult {
         Ok(())
     }
@@ -166,7 +1133,7 @@ mod tests {
     fn send<T: Send>(_t: T) {}
 
     #[test]
-    #[wasm_bindgen_test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
     fn manual_system_run() {
         let mut world = World::default();
         world.initialize::<u32>();
@@ -174,7 +1141,324 @@ mod tests {
     }
 
     #[test]
-    #[wasm_bindgen_test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn shared_duplicate_types() {
+        fn sys(a: &u32, b: &u32) -> SystemResult {
+            assert_eq!(a, b);
+            Ok(())
+        }
+        let mut world = World::default();
+        let mut system = sys.system();
+        system.initialize(&mut world);
+        system.run(&world).unwrap();
+        let mut dispatch = DispatcherBuilder::new()
+            .add(sys)
+            .add(|_a: &u32, _b: &u64, _c: &u32| Ok(()))
+            .build(&mut world);
+        assert_eq!(dispatch.iter_stages().count(), 1);
+        dispatch.run_seq(&world).unwrap();
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn conflicting_lock() {
+        let world = World::default();
+        let system = (|_a: &u32, _b: &mut u64, _c: &mut u32| Ok(())).system();
+        let mut locks = vec![];
+        match (system.lock)(&world, &mut locks) {
+            Err(EcsError::ConflictingAccess { type_name }) => assert_eq!(type_name, "u32"),
+            _ => unreachable!(),
+        }
+        let system = (|_b: &mut u64, _c: &mut u64| Ok(())).system();
+        match (system.lock)(&world, &mut locks) {
+            Err(EcsError::ConflictingAccess { type_name }) => assert_eq!(type_name, "u64"),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "`u32`")]
+    fn mutable_duplicate_types() {
+        let mut world = World::default();
+        DispatcherBuilder::new()
+            .add(|_a: &u32, _b: &mut u32| Ok(()))
+            .build(&mut world);
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn world_argument() {
+        #[derive(Default)]
+        struct A(u32);
+        #[derive(Default)]
+        struct B(u32);
+        fn sys(a: &A, world: &World) -> SystemResult {
+            assert_eq!(a.0, world.get::<B>()?.0);
+            Ok(())
+        }
+        let mut world = World::default();
+        let mut system = sys.system();
+        system.initialize(&mut world);
+        assert!(system.run(&world).is_err());
+        world.initialize::<B>();
+        system.run(&world).unwrap();
+        assert!(world.get::<World>().is_err());
+
+        let mut dispatch = DispatcherBuilder::new()
+            .add(|_a: &A| Ok(()))
+            .add(sys)
+            .add(|_w: &World, _a: &A| Ok(()))
+            .add(|b: &mut B| {
+                b.0 = 1;
+                Ok(())
+            })
+            .add(|a: &mut A| {
+                a.0 = 1;
+                Ok(())
+            })
+            .add(sys)
+            .build(&mut world);
+        assert_eq!(dispatch.iter_stages().count(), 3);
+        dispatch.run_seq(&world).unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn world_argument_with_mut() {
+        let _ = (|_w: &World, _a: &mut u32| Ok(())).system();
+    }
+
+    #[test]
+    #[should_panic]
+    fn mut_world_argument() {
+        let _ = (|_w: &mut World| Ok(())).system();
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn validate_against() {
+        let mut world = World::default();
+        let sys = (|_: &World, _: &u32, _: &u64| Ok(())).system();
+        world.initialize::<u32>();
+        match sys.validate_against(&world) {
+            Err(EcsError::ResourceNotFound { type_name }) => assert_eq!(type_name, "u64"),
+            _ => unreachable!(),
+        }
+        world.initialize::<u64>();
+        let _long_borrow = world.get_mut::<u64>().unwrap();
+        assert!(sys.validate_against(&world).is_ok());
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn repeating_system() {
+        #[derive(Default)]
+        struct Counter(u32);
+        fn count(counter: &mut Counter) -> SystemResult<SystemControl> {
+            counter.0 += 1;
+            if counter.0 < 5 {
+                Ok(SystemControl::Repeat)
+            } else {
+                Ok(SystemControl::Continue)
+            }
+        }
+        let mut world = World::default();
+        let mut sys = count.system().repeating(4);
+        sys.initialize(&mut world);
+        sys.run(&world).unwrap();
+        assert_eq!(world.get::<Counter>().unwrap().0, 5);
+        sys.run(&world).unwrap();
+        assert_eq!(world.get::<Counter>().unwrap().0, 6);
+
+        world.get_mut::<Counter>().unwrap().0 = 0;
+        let mut sys = count.system().repeating(3);
+        assert!(matches!(
+            sys.run(&world),
+            Err(EcsError::MaxIterationsExceeded(3))
+        ));
+        assert_eq!(world.get::<Counter>().unwrap().0, 4);
+    }
+
+    #[test]
+    fn system_access() {
+        #[derive(Default)]
+        struct A;
+        #[derive(Default)]
+        struct B;
+        let system = (|_a: &A, _b: &mut B| Ok(())).system();
+        assert_eq!(system.reads(), &[TypeId::of::<A>()]);
+        assert_eq!(system.writes(), &[TypeId::of::<B>()]);
+        assert_eq!(system.reads_names(), &[std::any::type_name::<A>()]);
+        assert_eq!(system.writes_names(), &[std::any::type_name::<B>()]);
+        assert_eq!(system.arity(), 2);
+        let system = (|| Ok(())).system();
+        assert!(system.reads().is_empty());
+        assert!(system.writes().is_empty());
+        assert_eq!(system.arity(), 0);
+    }
+
+    #[cfg(feature = "flexible_ordering")]
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn flexible_ordering() {
+        #[derive(Default)]
+        struct A(u32);
+        #[derive(Default)]
+        struct B(u32);
+        fn sys(a: &mut A, b: &B, c: &mut u64) -> SystemResult {
+            a.0 += b.0;
+            *c += 1;
+            Ok(())
+        }
+        let mut world = World::default();
+        let mut s = sys.system();
+        assert_eq!(s.reads(), &[TypeId::of::<B>()]);
+        assert_eq!(s.writes(), &[TypeId::of::<A>(), TypeId::of::<u64>()]);
+        s.initialize(&mut world);
+        world.get_mut::<B>().unwrap().0 = 2;
+        s.run(&world).unwrap();
+        assert_eq!(world.get::<A>().unwrap().0, 2);
+        assert_eq!(*world.get::<u64>().unwrap(), 1);
+        let mut dispatch = DispatcherBuilder::new()
+            .add(|_a: &mut A, _b: &B| Ok(()))
+            .add(|_b: &mut B, _a: &A| Ok(()))
+            .build(&mut world);
+        assert_eq!(dispatch.waves().len(), 2);
+        dispatch.run(&mut world).unwrap();
+    }
+
+    #[cfg(feature = "anyhow")]
+    #[test]
+    fn anyhow_system() {
+        #[derive(Default)]
+        struct Path(String);
+        fn read(path: &Path, contents: &mut String) -> anyhow::Result<usize> {
+            *contents = std::fs::read_to_string(&path.0)?;
+            Ok(contents.len())
+        }
+        let mut world = World::default();
+        let mut system = Anyhow(read).system();
+        assert!(system.name().ends_with("read"));
+        system.initialize(&mut world);
+        world.get_mut::<Path>().unwrap().0 = "/nonexistent/world_dispatcher".to_string();
+        match system.run(&world) {
+            Err(EcsError::SystemError(e)) => {
+                let io = std::fs::read_to_string(&world.get::<Path>().unwrap().0);
+                assert_eq!(e.to_string(), io.unwrap_err().to_string());
+            }
+            _ => unreachable!(),
+        }
+        world.get_mut::<Path>().unwrap().0 =
+            concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml").to_string();
+        assert!(system.run(&world).unwrap() > 0);
+        assert!(!world.get::<String>().unwrap().is_empty());
+        DispatcherBuilder::new()
+            .add(Anyhow(|_a: &Path| Ok(())))
+            .build(&mut world)
+            .run(&mut world)
+            .unwrap();
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn bound_method() {
+        struct Service {
+            step: u32,
+        }
+        impl Service {
+            fn update(&self, scale: &u32, total: &mut u64) -> SystemResult<u64> {
+                *total += (self.step * *scale) as u64;
+                Ok(*total)
+            }
+        }
+        fn assert_send<T: Send>(_: &T) {}
+        let mut world = World::default();
+        let mut system = bind(Service { step: 2 }, Service::update).system();
+        assert_send(&system);
+        assert!(system.name().ends_with("Service::update"));
+        system.initialize(&mut world);
+        *world.get_mut::<u32>().unwrap() = 3;
+        assert_eq!(system.run(&world).unwrap(), 6);
+        assert_eq!(system.run(&world).unwrap(), 12);
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn then_system() {
+        fn guard(enabled: &bool, count: &mut u32) -> SystemResult {
+            *count += 1;
+            ensure!(*enabled, "disabled");
+            Ok(())
+        }
+        fn dependent(count: &u32, total: &mut u64) -> SystemResult<u64> {
+            *total += *count as u64;
+            Ok(*total)
+        }
+        let mut world = World::default();
+        let mut system = then(guard.system(), dependent.system());
+        assert_eq!(system.reads(), &[TypeId::of::<bool>()]);
+        assert_eq!(system.writes(), &[TypeId::of::<u32>(), TypeId::of::<u64>()]);
+        system.initialize(&mut world);
+        assert!(system.run(&world).is_err());
+        assert_eq!(*world.get::<u64>().unwrap(), 0);
+        *world.get_mut::<bool>().unwrap() = true;
+        assert_eq!(system.run(&world).unwrap(), 2);
+
+        let mut locks = vec![];
+        assert!((system.lock)(&world, &mut locks).is_ok());
+        assert!(world.get::<u32>().is_err());
+        assert!(world.get::<bool>().is_ok());
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn system_returning_value() {
+        fn sys(a: &u32, b: &mut u64) -> SystemResult<u64> {
+            *b += *a as u64 + 1;
+            Ok(*b)
+        }
+        let mut world = World::default();
+        let mut system = sys.system();
+        system.initialize(&mut world);
+        assert_eq!(system.run(&world).unwrap(), 1);
+        assert_eq!(system.run(&world).unwrap(), 2);
+        let mut system = (|a: &u32| Ok(*a == 0)).system();
+        assert!(system.run(&world).unwrap());
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    fn system_retries() {
+        #[derive(Debug)]
+        struct Flaky;
+        impl std::fmt::Display for Flaky {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "Flaky")
+            }
+        }
+        impl Error for Flaky {}
+        fn sys(attempts: &mut u32) -> SystemResult {
+            *attempts += 1;
+            if *attempts != 3 && *attempts != 6 {
+                system_error!(Flaky);
+            }
+            Ok(())
+        }
+        let mut world = World::default();
+        let mut system = sys.system().with_retries(2);
+        system.initialize(&mut world);
+        system.run(&world).unwrap();
+        assert_eq!(*world.get::<u32>().unwrap(), 3);
+
+        let mut system = sys.system().with_retries(1);
+        assert!(system.run(&world).is_err());
+        assert_eq!(*world.get::<u32>().unwrap(), 5);
+        system.run(&world).unwrap();
+        assert_eq!(*world.get::<u32>().unwrap(), 6);
+    }
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
     fn system_replace_resource() {
         #[derive(Default)]
         pub struct A;
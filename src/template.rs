@@ -0,0 +1,318 @@
+use crate::*;
+
+/// The parts of a `System` shared by the dispatchers created from a
+/// `DispatcherTemplate`.
+#[derive(Clone)]
+struct SystemTemplate {
+    initialize: InitializeFn,
+    lock: LockFn,
+    run_factory: RunFactory<()>,
+    name: &'static str,
+    access: Access,
+    label: Option<&'static str>,
+    main_thread: bool,
+}
+
+impl SystemTemplate {
+    fn new(sys: &System) -> Result<Self, ScheduleError> {
+        let run_factory = sys
+            .run_factory
+            .clone()
+            .ok_or_else(|| ScheduleError::NotShareable {
+                system: sys.name.to_string(),
+            })?;
+        Ok(Self {
+            initialize: sys.initialize.clone(),
+            lock: sys.lock.clone(),
+            run_factory,
+            name: sys.name,
+            access: sys.access.clone(),
+            label: sys.label,
+            main_thread: sys.main_thread,
+        })
+    }
+
+    fn instantiate(&self) -> System {
+        System {
+            initialize: self.initialize.clone(),
+            lock: self.lock.clone(),
+            run_fn: (self.run_factory)(),
+            run_factory: Some(self.run_factory.clone()),
+            name: self.name,
+            access: self.access.clone(),
+            label: self.label,
+            main_thread: self.main_thread,
+        }
+    }
+}
+
+/// A schedule built by `DispatcherBuilder::build_template`, from which
+/// independent `Dispatcher`s are created cheaply, for example to run the
+/// same systems on multiple worlds in parallel.
+///
+/// The systems of every dispatcher share their functions, and keep their own
+/// state, such as their retries and profiles.
+#[derive(Clone)]
+pub struct DispatcherTemplate {
+    stages: Vec<Vec<SystemTemplate>>,
+    shutdown: Vec<SystemTemplate>,
+    initialization_order: Vec<&'static str>,
+    auto_initialize: bool,
+    profiling: bool,
+    hooks: Vec<SystemRunHook>,
+    run_criteria: Option<RunCriteriaFn>,
+    abort: Option<RunCriteriaFn>,
+    label: Option<&'static str>,
+}
+
+impl DispatcherBuilder {
+    /// Builds a `DispatcherTemplate` from the accumulated set of `System`,
+    /// with the same stages as `build`, which are computed once using the
+    /// given `World`.
+    ///
+    /// Panics if the systems can't be scheduled, see `try_build_template`.
+    pub fn build_template(self, world: &mut World) -> DispatcherTemplate {
+        self.try_build_template(world)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Like `build_template`, but returns a `ScheduleError` if the systems
+    /// can't be scheduled, including when the builder has exclusive systems
+    /// or systems created manually without a `run_factory`.
+    pub fn try_build_template(
+        mut self,
+        world: &mut World,
+    ) -> Result<DispatcherTemplate, ScheduleError> {
+        if !self.exclusive.is_empty() {
+            return Err(ScheduleError::ExclusiveInTemplate);
+        }
+        let auto_initialize = self.auto_initialize;
+        let profiling = std::mem::replace(&mut self.profiling, false);
+        let hooks = std::mem::take(&mut self.hooks);
+        let dispatcher = self.try_build(world)?;
+        Ok(DispatcherTemplate {
+            stages: dispatcher
+                .stages
+                .iter()
+                .map(|stage| stage.iter().map(SystemTemplate::new).collect())
+                .collect::<Result<_, _>>()?,
+            shutdown: dispatcher
+                .shutdown
+                .iter()
+                .map(SystemTemplate::new)
+                .collect::<Result<_, _>>()?,
+            initialization_order: dispatcher.initialization_order,
+            auto_initialize,
+            profiling,
+            hooks,
+            run_criteria: dispatcher.run_criteria,
+            abort: dispatcher.abort,
+            label: dispatcher.label,
+        })
+    }
+}
+
+impl DispatcherTemplate {
+    /// Creates a new `Dispatcher` running the systems of this template,
+    /// initializing their resources in the given `World` unless the
+    /// template was built with `DispatcherBuilder::auto_initialize(false)`.
+    pub fn instantiate(&self, world: &mut World) -> Dispatcher {
+        let mut stages: Vec<Vec<System>> = self
+            .stages
+            .iter()
+            .map(|stage| stage.iter().map(SystemTemplate::instantiate).collect())
+            .collect();
+        let mut shutdown: Vec<System> = self
+            .shutdown
+            .iter()
+            .map(SystemTemplate::instantiate)
+            .collect();
+        if self.auto_initialize {
+            for sys in stages.iter().flatten().chain(shutdown.iter()) {
+                sys.initialize(world);
+            }
+        }
+        let mut profiles = vec![];
+        if self.profiling {
+            profiles = stages
+                .iter_mut()
+                .flatten()
+                .chain(shutdown.iter_mut())
+                .map(profile_system)
+                .collect();
+        }
+        for sys in stages.iter_mut().flatten().chain(shutdown.iter_mut()) {
+            hook_system(sys, &self.hooks);
+        }
+        Dispatcher {
+            exclusive: stages.iter().map(|_| vec![]).collect(),
+            stages,
+            shutdown,
+            initialization_order: self.initialization_order.clone(),
+            resume: 0,
+            step: 0,
+            disabled: vec![],
+            world_id: world.id(),
+            run_criteria: self.run_criteria.clone(),
+            abort: self.abort.clone(),
+            label: self.label,
+            flush_points: vec![],
+            profiles,
+            hooks: self.hooks.clone(),
+        }
+    }
+}
+
+impl Dispatcher {
+    /// Creates a new `DispatcherBuilder` with the systems of this dispatcher,
+    /// in the order of their stages, along with its shutdown systems, run
+    /// criteria, abort predicate, label, profiling and hooks. The builder can be modified further, and
+    /// computes the stages again when built.
+    ///
+    /// The systems share their functions with this dispatcher through their
+    /// `run_factory`, and start over with fresh state. Fails with
+    /// `ScheduleError::NotShareable` for systems created manually without a
+    /// `run_factory`, and `ScheduleError::ExclusiveInTemplate` if the
+    /// dispatcher has exclusive systems or flush points, whose functions
+    /// aren't shared.
+    pub fn to_builder(&self) -> Result<DispatcherBuilder, ScheduleError> {
+        if self.exclusive.iter().any(|after| !after.is_empty()) {
+            return Err(ScheduleError::ExclusiveInTemplate);
+        }
+        let copy = |systems: &mut dyn Iterator<Item = &System>| {
+            systems
+                .map(|sys| SystemTemplate::new(sys).map(|sys| sys.instantiate()))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let mut builder = DispatcherBuilder::new();
+        builder.systems = copy(&mut self.stages.iter().flatten())?;
+        builder.shutdown = copy(&mut self.shutdown.iter())?;
+        builder.profiling = !self.profiles.is_empty();
+        builder.hooks = self.hooks.clone();
+        builder.run_criteria = self.run_criteria.clone();
+        builder.abort = self.abort.clone();
+        builder.label = self.label;
+        Ok(builder)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn template_instances() {
+        #[derive(Default)]
+        struct Count(u32);
+        fn inc(step: &u32, count: &mut Count) -> SystemResult {
+            count.0 += step;
+            Ok(())
+        }
+        fn flaky(count: &Count) -> SystemResult {
+            ensure!(count.0 > 0, EcsError::NotInitialized);
+            Ok(())
+        }
+        let mut world = World::default();
+        let template = DispatcherBuilder::new()
+            .add(inc)
+            .add_system(flaky.system().with_retries(1))
+            .profiling()
+            .build_template(&mut world);
+        let mut worlds = [World::default(), World::default(), World::default()];
+        let mut dispatchers = vec![];
+        for (i, world) in worlds.iter_mut().enumerate() {
+            world.initialize::<u32>();
+            *world.get_mut::<u32>().unwrap() = i as u32 + 1;
+            dispatchers.push(template.clone().instantiate(world));
+        }
+        std::thread::scope(|scope| {
+            for (world, dispatcher) in worlds.iter_mut().zip(dispatchers.iter_mut()) {
+                scope.spawn(move || {
+                    for _ in 0..2 {
+                        dispatcher.run_seq(world).unwrap();
+                    }
+                });
+            }
+        });
+        for (i, world) in worlds.iter().enumerate() {
+            assert_eq!(world.get::<Count>().unwrap().0, 2 * (i as u32 + 1));
+        }
+        for dispatcher in &dispatchers {
+            let report = dispatcher.profiling_report();
+            assert_eq!(report.len(), 2);
+            assert!(report.iter().all(|profile| profile.runs == 2));
+        }
+    }
+
+    #[test]
+    fn to_builder() {
+        let mut world = World::default();
+        let dispatcher = DispatcherBuilder::new()
+            .add_system(
+                (|v: &mut u32| {
+                    *v += 1;
+                    Ok(())
+                })
+                .system()
+                .with_label("inc"),
+            )
+            .add(|_: &u32, _: &u64| Ok(()))
+            .build(&mut world);
+        let mut copy = dispatcher
+            .to_builder()
+            .unwrap()
+            .add(|v: &mut u64| {
+                *v += 1;
+                Ok(())
+            })
+            .build(&mut world);
+        assert_eq!(copy.stages.len(), 3);
+        assert_eq!(copy.stages[0][0].label(), Some("inc"));
+        copy.run_seq(&world).unwrap();
+        assert_eq!(*world.get::<u32>().unwrap(), 1);
+        assert_eq!(*world.get::<u64>().unwrap(), 1);
+
+        let with_flush = DispatcherBuilder::new()
+            .add(|_: &u32| Ok(()))
+            .add_flush_point("flush")
+            .build(&mut world);
+        assert!(matches!(
+            with_flush.to_builder(),
+            Err(ScheduleError::ExclusiveInTemplate)
+        ));
+    }
+
+    #[test]
+    fn template_exclusive_system() {
+        let result = DispatcherBuilder::new()
+            .add_exclusive(|_: &mut World| Ok(()))
+            .try_build_template(&mut World::default());
+        match result {
+            Err(error @ ScheduleError::ExclusiveInTemplate) => assert_eq!(
+                error.to_string(),
+                "Exclusive systems cannot be used in a template."
+            ),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "System `manual` cannot be used in a template, because it has no `run_factory`."
+    )]
+    fn template_manual_system() {
+        let sys = System {
+            initialize: std::sync::Arc::new(|_| {}),
+            lock: std::sync::Arc::new(|_, _| Ok(())),
+            run_fn: Box::new(|_| Ok(())),
+            run_factory: None,
+            name: "manual",
+            access: Access::default(),
+            label: None,
+            main_thread: false,
+        };
+        DispatcherBuilder::new()
+            .add_system(sys)
+            .build_template(&mut World::default());
+    }
+}
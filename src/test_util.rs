@@ -0,0 +1,94 @@
+use crate::*;
+use std::fmt::Debug;
+
+/// Checks that the systems of a dispatcher end with the same state whatever
+/// the order in which they run, to validate that a parallel schedule is
+/// actually order-independent.
+///
+/// Each of the `iterations` builds a dispatcher with `builder` and a world
+/// with `world`, and runs it once with `Dispatcher::run_seq`, then does the
+/// same with `Dispatcher::run_ref`, which runs in parallel if the `parallel`
+/// or `std_parallel` feature is enabled. The value returned by `state` for
+/// each world must then be the same as after the first sequential run.
+///
+/// Like `run_ref`, exclusive systems are not run and `Commands` are not
+/// applied. Panics if a run fails or if the states differ.
+///
+/// ```
+/// use world_dispatcher::*;
+///
+/// test_util::assert_deterministic(
+///     || {
+///         DispatcherBuilder::new()
+///             .add(|a: &mut u32| {
+///                 *a += 1;
+///                 Ok(())
+///             })
+///             .add(|a: &u32, b: &mut u64| {
+///                 *b += *a as u64;
+///                 Ok(())
+///             })
+///     },
+///     World::default,
+///     |world| (*world.get::<u32>().unwrap(), *world.get::<u64>().unwrap()),
+///     10,
+/// );
+/// ```
+pub fn assert_deterministic<S: PartialEq + Debug>(
+    builder: impl Fn() -> DispatcherBuilder,
+    world: impl Fn() -> World,
+    state: impl Fn(&World) -> S,
+    iterations: usize,
+) {
+    let mut expected = None;
+    for iteration in 0..iterations {
+        for parallel in [false, true] {
+            let mut world = world();
+            let mut dispatcher = builder().build(&mut world);
+            let result = if parallel {
+                dispatcher.run_ref(&world)
+            } else {
+                dispatcher.run_seq(&world)
+            };
+            let mode = if parallel { "parallel" } else { "sequential" };
+            if let Err(error) = result {
+                panic!("The {} run {} failed: {}", mode, iteration, error);
+            }
+            let state = state(&world);
+            match &expected {
+                None => expected = Some(state),
+                Some(expected) => assert_eq!(
+                    expected, &state,
+                    "The {} run {} ended with a different state.",
+                    mode, iteration
+                ),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::*;
+
+    #[test]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+    #[should_panic(expected = "ended with a different state")]
+    fn nondeterministic() {
+        use std::sync::atomic::{AtomicU32, Ordering};
+        static RUNS: AtomicU32 = AtomicU32::new(0);
+        assert_deterministic(
+            || {
+                DispatcherBuilder::new().add(|a: &mut u32| {
+                    *a = RUNS.fetch_add(1, Ordering::Relaxed);
+                    Ok(())
+                })
+            },
+            World::default,
+            |world| *world.get::<u32>().unwrap(),
+            2,
+        );
+    }
+}
@@ -1,5 +1,8 @@
 use crate::*;
 
+/// A `HashMap` indexed by `TypeId`, using the `TypeIdHasher`.
+pub(crate) type TypeIdMap<V> = HashMap<TypeId, V, BuildHasherDefault<TypeIdHasher>>;
+
 /// A hasher for `TypeId<T>`.
 /// According to benchmarks, using it seems to increase performance
 /// in most cases. Sometimes not. Computers are weird.
@@ -15,4 +18,10 @@ impl Hasher for TypeIdHasher {
         use core::convert::TryInto;
         self.0 = u64::from_ne_bytes(bytes.try_into().unwrap());
     }
+
+    /// `TypeId` hashes itself as a single `u64`, which is used as the hash
+    /// as is.
+    fn write_u64(&mut self, i: u64) {
+        self.0 = i;
+    }
 }
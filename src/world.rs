@@ -5,57 +5,666 @@ use crate::*;
 /// at runtime:
 /// - The same type cannot be borrowed immutably and mutably at the same time.
 /// - The same type cannot be borrowed mutably more than once at the same time.
+/// - The same type can be borrowed immutably any number of times at the same
+///   time, including re-entrantly, while a guard is alive in an enclosing
+///   scope.
+///
+/// Borrows of different types never interfere, and a type can be borrowed
+/// again in any way once all of its guards are dropped.
 #[derive(Default)]
 pub struct World {
-    pub(crate) res:
-        HashMap<TypeId, AtomicRefCell<Box<dyn Resource>>, BuildHasherDefault<TypeIdHasher>>,
+    /// The unique id of this world.
+    id: WorldId,
+    pub(crate) res: TypeIdMap<ResourceCell>,
+    /// The type names of the resources in `res`.
+    pub(crate) names: TypeIdMap<&'static str>,
+    /// The shallow size of the resources in `res`.
+    pub(crate) sizes: TypeIdMap<usize>,
+    /// The tick at which each resource in `res` last changed.
+    pub(crate) ticks: TypeIdMap<std::sync::atomic::AtomicU64>,
+    /// The last change tick given out.
+    pub(crate) tick: std::sync::atomic::AtomicU64,
+    /// Incremented whenever resources are inserted or removed, which can move
+    /// the other ones, see `ResourceHandle`.
+    pub(crate) generation: u64,
+    /// The default constructors recorded by `initialize`, for `reset`.
+    pub(crate) defaults: TypeIdMap<fn() -> Box<dyn Resource>>,
+    /// The types registered with `register_change_detect`.
+    pub(crate) change_detect: TypeIdMap<ChangeDetect>,
+    /// Trait object casts registered with `register_as`, indexed by the
+    /// `TypeId` of the trait object type.
+    pub(crate) casts: TypeIdMap<Vec<(TypeId, Box<dyn Any + Send + Sync>)>>,
+    /// Shared trait object casts registered with `register_dyn`, indexed by
+    /// the `TypeId` of the trait object type.
+    pub(crate) ref_casts: TypeIdMap<Vec<(TypeId, Box<dyn Any + Send + Sync>)>>,
+    /// The clone functions of the types registered with `register_clone`.
+    pub(crate) clones: TypeIdMap<CloneFn>,
+    /// The number of reads and writes of each resource.
+    #[cfg(feature = "access_stats")]
+    pub(crate) access_stats: Mutex<TypeIdMap<AccessCount>>,
+    /// The number of guards extended by `System::lock` that are still alive,
+    /// see `World::outstanding_borrows`.
+    #[cfg(debug_assertions)]
+    pub(crate) lock_guards: std::sync::atomic::AtomicUsize,
+}
+
+/// Creates the default value of a resource, recorded by `World::initialize`.
+fn default_resource<T: Default + Send + Sync + 'static>() -> Box<dyn Resource> {
+    Box::new(T::default())
+}
+
+/// A type-erased cast from a resource to a trait object, created by
+/// `World::register_as`.
+type CastMut<D> = Box<dyn Fn(&mut dyn Resource) -> &mut D + Send + Sync>;
+
+/// Identifies a `World`, see `World::id`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct WorldId(u64);
+
+impl Default for WorldId {
+    /// Returns a new id, different from all the previous ones.
+    fn default() -> Self {
+        static NEXT: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+        WorldId(NEXT.fetch_add(1, std::sync::atomic::Ordering::Relaxed))
+    }
+}
+
+/// A type-erased update of a single resource, run by `World::batch_mut`.
+pub struct ResourceUpdate<'a> {
+    type_id: TypeId,
+    update: Box<dyn FnOnce(&World) -> SystemResult + 'a>,
+}
+
+impl<'a> ResourceUpdate<'a> {
+    /// Creates an update running `f` on the resource of type `T`.
+    pub fn new<T: Send + Sync + 'static>(f: impl FnOnce(&mut T) -> SystemResult + 'a) -> Self {
+        Self {
+            type_id: TypeId::of::<T>(),
+            update: Box::new(|world: &World| world.get_mut_map::<T, _>(f)?),
+        }
+    }
+
+    /// Returns the type id of the updated resource.
+    pub fn type_id(&self) -> TypeId {
+        self.type_id
+    }
 }
 
 impl World {
-    /// Initializes a resource to its default value.
-    /// This is the only way to "insert" a resource.
+    /// Returns the id of this world, which is unique among all the worlds
+    /// created by the program.
+    pub fn id(&self) -> WorldId {
+        self.id
+    }
+
+    /// Returns the name used for `T` in error messages and by
+    /// `resource_names`.
+    pub fn type_name<T: ?Sized>() -> &'static str {
+        std::any::type_name::<T>()
+    }
+    /// Returns the type names of the resources in the world, in no
+    /// particular order.
+    pub fn resource_names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.names.values().copied()
+    }
+    /// Returns the type name and size in bytes of each resource, in no
+    /// particular order.
+    ///
+    /// This is a shallow estimate, like `std::mem::size_of_val`: the heap
+    /// allocations owned by a resource, such as the contents of a `Vec`, are
+    /// not counted.
+    pub fn resource_size_hint(&self) -> Vec<(&'static str, usize)> {
+        self.sizes
+            .iter()
+            .map(|(id, size)| (self.names[id], *size))
+            .collect()
+    }
+    /// Initializes a resource to its default value, if it isn't already
+    /// present. Also records how to create the default value, for `reset`.
     ///
     /// It is suggested to use a macro to collect all
     /// the resources and initialize all of them.
     pub fn initialize<T: Default + Send + Sync + 'static>(&mut self) {
+        self.defaults
+            .insert(TypeId::of::<T>(), default_resource::<T>);
         if !self.res.contains_key(&TypeId::of::<T>()) {
-            self.res.insert(
-                TypeId::of::<T>(),
-                AtomicRefCell::new(Box::new(T::default())),
-            );
+            self.assert_unborrowed::<T>();
+            self.storage_changed();
         }
+        self.res
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| ResourceCell::new(Box::new(T::default())));
+        self.names
+            .entry(TypeId::of::<T>())
+            .or_insert_with(Self::type_name::<T>);
+        self.sizes
+            .insert(TypeId::of::<T>(), std::mem::size_of::<T>());
+        self.insert_tick(TypeId::of::<T>());
     }
+    /// Replaces a resource by its default value, or inserts it if it was
+    /// removed, whatever the value was inserted with.
+    ///
+    /// This requires the type to have been initialized at least once, with
+    /// `initialize` or by a dispatcher, which records how to create its
+    /// default value. Returns false and does nothing otherwise.
+    pub fn reset<T: Send + Sync + 'static>(&mut self) -> bool {
+        let typeid = TypeId::of::<T>();
+        let default = match self.defaults.get(&typeid) {
+            Some(default) => *default,
+            None => return false,
+        };
+        match self.res.get_mut(&typeid) {
+            Some(res) => {
+                *res.get_mut() = default();
+                self.forget_change(&typeid);
+                self.bump_tick(&typeid);
+            }
+            None => {
+                let _ = self.insert_boxed_checked(default());
+            }
+        }
+        true
+    }
+    /// Inserts a resource, unless a resource of the same type is already
+    /// present.
+    /// Returns whether the value was inserted.
+    pub fn insert_if_absent<T: Send + Sync + 'static>(&mut self, value: T) -> bool {
+        if self.res.contains_key(&TypeId::of::<T>()) {
+            return false;
+        }
+        self.assert_unborrowed::<T>();
+        self.storage_changed();
+        self.res
+            .insert(TypeId::of::<T>(), ResourceCell::new(Box::new(value)));
+        self.names.insert(TypeId::of::<T>(), Self::type_name::<T>());
+        self.sizes
+            .insert(TypeId::of::<T>(), std::mem::size_of::<T>());
+        self.insert_tick(TypeId::of::<T>());
+        true
+    }
+    /// Inserts a resource, replacing any resource of the same type, and
+    /// returns the world. Useful to set up a world in a single expression:
+    /// ```
+    /// use world_dispatcher::*;
+    ///
+    /// let world = World::default().with_resource(3u32).with_resource("config");
+    /// assert_eq!(*world.get::<u32>().unwrap(), 3);
+    /// ```
+    pub fn with_resource<T: Send + Sync + 'static>(mut self, value: T) -> Self {
+        match self.res.get_mut(&TypeId::of::<T>()) {
+            Some(res) => {
+                *res.get_mut() = Box::new(value);
+                self.forget_change(&TypeId::of::<T>());
+                self.bump_tick(&TypeId::of::<T>());
+            }
+            None => {
+                self.insert_if_absent(value);
+            }
+        }
+        self
+    }
+    /// Inserts a type-erased resource, unless a resource of the same type is
+    /// already present.
+    ///
+    /// The type of the resource is taken from the boxed value itself, so it
+    /// can later be borrowed with `get` using its concrete type. This is the
+    /// recommended way to insert resources whose type is only known at
+    /// runtime.
+    pub fn insert_boxed_checked(&mut self, value: Box<dyn Resource>) -> Result<(), BoxedError> {
+        // Deref the box, which is a `Resource` itself.
+        let typeid = (*value).as_any().type_id();
+        if self.res.contains_key(&typeid) {
+            return Err(BoxedError::AlreadyPresent(value));
+        }
+        self.assert_unborrowed_named((*value).resource_type_name());
+        self.names.insert(typeid, (*value).resource_type_name());
+        self.sizes.insert(typeid, std::mem::size_of_val(&*value));
+        self.insert_tick(typeid);
+        self.storage_changed();
+        self.res.insert(typeid, ResourceCell::new(value));
+        Ok(())
+    }
+    /// Panics in debug builds if a resource is borrowed while inserting `T`.
+    ///
+    /// Inserting a resource may move all the others, which is only possible
+    /// while they are borrowed if a borrow was leaked, or if `&mut World` was
+    /// obtained unsafely while a system runs, which would be unsound.
+    fn assert_unborrowed<T: ?Sized>(&self) {
+        self.assert_unborrowed_named(Self::type_name::<T>());
+    }
+
+    fn assert_unborrowed_named(&self, inserted: &str) {
+        #[cfg(debug_assertions)]
+        for (id, res) in self.res.iter() {
+            if res.try_borrow_mut().is_err() {
+                panic!(
+                    "Cannot insert `{}` into the `World` while `{}` is borrowed, \
+                    as inserting may move the borrowed resources.",
+                    inserted,
+                    self.names.get(id).copied().unwrap_or("<unknown>")
+                );
+            }
+        }
+        #[cfg(not(debug_assertions))]
+        let _ = inserted;
+    }
+
+    /// Inserts type-erased resources, replacing the resources of the same
+    /// types, for example those loaded by a plugin host or drained from
+    /// another world with `drain_resources`.
+    ///
+    /// Like `insert_boxed_checked`, the type of each resource is taken from
+    /// the boxed value itself. Panics in debug builds if a resource is
+    /// borrowed, which can only happen if a borrow was leaked.
+    pub fn extend_boxed(&mut self, items: impl IntoIterator<Item = Box<dyn Resource>>) {
+        for value in items {
+            let typeid = (*value).as_any().type_id();
+            self.assert_unborrowed_named((*value).resource_type_name());
+            self.sizes.insert(typeid, std::mem::size_of_val(&*value));
+            match self.res.get_mut(&typeid) {
+                Some(res) => {
+                    *res.get_mut() = value;
+                    self.forget_change(&typeid);
+                    self.bump_tick(&typeid);
+                }
+                None => {
+                    if let Err(BoxedError::AlreadyPresent(_)) = self.insert_boxed_checked(value) {
+                        unreachable!();
+                    }
+                }
+            }
+        }
+    }
+
     /// Get an immutable reference to a resource by type.
     /// Will return an error if the type is:
     /// - Non initialized
     /// - Already borrowed mutably
-    pub fn get<T: Send + Sync + 'static>(&self) -> Result<AtomicRef<T>, EcsError> {
+    ///
+    /// The returned `Res` derefs to the resource, and forwards `AsRef`,
+    /// `Borrow`, `Debug` and `Display` to it.
+    #[cfg_attr(feature = "debug_borrows", track_caller)]
+    pub fn get<T: Send + Sync + 'static>(&self) -> Result<Res<'_, T>, EcsError> {
+        #[cfg(feature = "debug_borrows")]
+        let caller = std::panic::Location::caller();
+        #[cfg(feature = "access_stats")]
+        self.count_access::<T>(false);
+        let typeid = TypeId::of::<T>();
         self.res
-            .get(&TypeId::of::<T>())
+            .get(&typeid)
             .ok_or(EcsError::NotInitialized)
-            .and_then(|i| i.try_borrow().map_err(|_| EcsError::AlreadyBorrowed))
-            .and_then(|i| Ok(AtomicRef::map(i, |j| j.downcast_ref::<T>().unwrap())))
+            .and_then(|i| i.try_borrow().map_err(|_| self.already_borrowed(&typeid)))
+            .map(|i| {
+                #[cfg(feature = "debug_borrows")]
+                let i = self.record_borrower(typeid, i, caller);
+                Res::map(i, |j| j.downcast_ref::<T>().unwrap())
+            })
     }
     /// Get a mutable reference to a resource by type.
     /// Will return an error if the type is:
     /// - Non initialized
     /// - Already borrowed immutably
     /// - Already borrowed mutably
-    pub fn get_mut<T: Send + Sync + 'static>(&self) -> Result<AtomicRefMut<T>, EcsError> {
+    ///
+    /// Like `get`, the returned `ResMut` also derefs mutably, and forwards
+    /// `AsMut` and `BorrowMut`.
+    #[cfg_attr(feature = "debug_borrows", track_caller)]
+    pub fn get_mut<T: Send + Sync + 'static>(&self) -> Result<ResMut<'_, T>, EcsError> {
+        #[cfg(feature = "debug_borrows")]
+        let caller = std::panic::Location::caller();
+        #[cfg(feature = "access_stats")]
+        self.count_access::<T>(true);
+        let typeid = TypeId::of::<T>();
         self.res
-            .get(&TypeId::of::<T>())
+            .get(&typeid)
             .ok_or(EcsError::NotInitialized)
-            .and_then(|i| i.try_borrow_mut().map_err(|_| EcsError::AlreadyBorrowed))
-            .and_then(|i| Ok(AtomicRefMut::map(i, |j| j.downcast_mut::<T>().unwrap())))
+            .and_then(|i| {
+                i.try_borrow_mut().map_err(|_| {
+                    // Checked before `already_borrowed`, which would name a
+                    // shared borrower instead with `debug_borrows`.
+                    if i.try_borrow().is_ok() {
+                        EcsError::AlreadySharedBorrowed {
+                            type_name: Self::type_name::<T>(),
+                        }
+                    } else {
+                        self.already_borrowed(&typeid)
+                    }
+                })
+            })
+            .map(|i| {
+                #[cfg(feature = "debug_borrows")]
+                let i = self.record_borrower(typeid, i, caller);
+                let i = self.bump_tick_mut(&typeid, i);
+                ResMut::map(i, |j| j.downcast_mut::<T>().unwrap())
+            })
+    }
+
+    /// Get an immutable reference to a resource by type, if it is
+    /// initialized and not borrowed mutably.
+    ///
+    /// Unlike `get`, this doesn't tell the two cases apart, and doesn't build
+    /// an error.
+    #[cfg_attr(feature = "debug_borrows", track_caller)]
+    pub fn try_get<T: Send + Sync + 'static>(&self) -> Option<Res<'_, T>> {
+        #[cfg(feature = "debug_borrows")]
+        let caller = std::panic::Location::caller();
+        #[cfg(feature = "access_stats")]
+        self.count_access::<T>(false);
+        let i = self.res.get(&TypeId::of::<T>())?.try_borrow().ok()?;
+        #[cfg(feature = "debug_borrows")]
+        let i = self.record_borrower(TypeId::of::<T>(), i, caller);
+        Some(Res::map(i, |j| j.downcast_ref::<T>().unwrap()))
+    }
+
+    /// Get a mutable reference to a resource by type, if it is initialized
+    /// and not borrowed.
+    ///
+    /// Like `try_get`, this returns `None` instead of an error.
+    #[cfg_attr(feature = "debug_borrows", track_caller)]
+    pub fn try_get_mut<T: Send + Sync + 'static>(&self) -> Option<ResMut<'_, T>> {
+        #[cfg(feature = "debug_borrows")]
+        let caller = std::panic::Location::caller();
+        #[cfg(feature = "access_stats")]
+        self.count_access::<T>(true);
+        let i = self.res.get(&TypeId::of::<T>())?.try_borrow_mut().ok()?;
+        #[cfg(feature = "debug_borrows")]
+        let i = self.record_borrower(TypeId::of::<T>(), i, caller);
+        let i = self.bump_tick_mut(&TypeId::of::<T>(), i);
+        Some(ResMut::map(i, |j| j.downcast_mut::<T>().unwrap()))
+    }
+
+    /// Get an immutable view of a `Vec<T>` resource as a slice, for code
+    /// that only needs the elements. Systems can take `&Vec<T>`, which derefs
+    /// to `&[T]` the same way.
+    /// Will return an error if the type is:
+    /// - Non initialized
+    /// - Already borrowed mutably
+    #[cfg_attr(feature = "debug_borrows", track_caller)]
+    pub fn get_slice<T: Send + Sync + 'static>(&self) -> Result<Res<'_, [T]>, EcsError> {
+        self.get::<Vec<T>>().map(|i| Res::map(i, Vec::as_slice))
+    }
+
+    /// Get a mutable view of a `Vec<T>` resource as a slice, which can modify
+    /// the elements but not their number.
+    /// Will return an error if the type is:
+    /// - Non initialized
+    /// - Already borrowed immutably
+    /// - Already borrowed mutably
+    #[cfg_attr(feature = "debug_borrows", track_caller)]
+    pub fn get_slice_mut<T: Send + Sync + 'static>(&self) -> Result<ResMut<'_, [T]>, EcsError> {
+        self.get_mut::<Vec<T>>()
+            .map(|i| ResMut::map(i, Vec::as_mut_slice))
+    }
+
+    /// Get an immutable reference to a resource and a mutable reference to
+    /// another one at the same time.
+    /// Will return an error if:
+    /// - `A` and `B` are the same type
+    /// - Either type is non initialized, as `EcsError::ResourceNotFound`
+    /// - `A` is already borrowed mutably
+    /// - `B` is already borrowed immutably or mutably
+    #[cfg_attr(feature = "debug_borrows", track_caller)]
+    pub fn get_ref_and_mut<A: Send + Sync + 'static, B: Send + Sync + 'static>(
+        &self,
+    ) -> Result<(Res<'_, A>, ResMut<'_, B>), EcsError> {
+        check_conflicts(
+            &[TypeId::of::<A>()],
+            &[TypeId::of::<B>()],
+            &[Self::type_name::<B>()],
+        )?;
+        Ok((self.get_named::<A>()?, self.get_mut_named::<B>()?))
+    }
+
+    /// Get an immutable reference to a resource by type, panicking with a
+    /// descriptive message if it's missing or already borrowed mutably.
+    ///
+    /// This is meant for tests and prototypes, where it is more helpful than
+    /// unwrapping the result of `get`.
+    #[track_caller]
+    pub fn expect<T: Send + Sync + 'static>(&self) -> Res<'_, T> {
+        match self.get::<T>() {
+            Ok(r) => r,
+            Err(e) => self.expect_failed::<T>(e),
+        }
+    }
+    /// Get a mutable reference to a resource by type, panicking with a
+    /// descriptive message if it's missing or already borrowed.
+    ///
+    /// This is meant for tests and prototypes, where it is more helpful than
+    /// unwrapping the result of `get_mut`.
+    #[track_caller]
+    pub fn expect_mut<T: Send + Sync + 'static>(&self) -> ResMut<'_, T> {
+        match self.get_mut::<T>() {
+            Ok(r) => r,
+            Err(e) => self.expect_failed::<T>(e),
+        }
+    }
+    #[track_caller]
+    fn expect_failed<T>(&self, error: EcsError) -> ! {
+        if let EcsError::NotInitialized = error {
+            let mut names = self.resource_names().collect::<Vec<_>>();
+            names.sort_unstable();
+            panic!(
+                "Resource `{}` is not in the world. Available resources: {:?}",
+                Self::type_name::<T>(),
+                names
+            );
+        }
+        panic!(
+            "Resource `{}` cannot be borrowed: {:?}",
+            Self::type_name::<T>(),
+            error
+        );
+    }
+    /// Get an immutable reference to a resource by type, while other
+    /// immutable references to it may still be alive.
+    ///
+    /// Shared borrows are re-entrant: any number of them may be held at once,
+    /// including nested on the same thread, for example in helper functions
+    /// called with a guard alive. Exclusive borrows are not: while any shared
+    /// guard is alive, `get_mut` of the same type fails with
+    /// `EcsError::AlreadyBorrowed`, and while a `get_mut` guard is alive, this
+    /// fails the same way.
+    ///
+    /// This behaves exactly like `get`; it exists to spell out the intent at
+    /// call sites relying on re-entrancy.
+    /// Will return an error if the type is:
+    /// - Non initialized
+    /// - Already borrowed mutably
+    #[cfg_attr(feature = "debug_borrows", track_caller)]
+    pub fn get_shared_recursive<T: Send + Sync + 'static>(&self) -> Result<Res<'_, T>, EcsError> {
+        self.get::<T>()
+    }
+
+    /// Returns whether the resource `T` is currently borrowed, immutably or
+    /// mutably. Returns false if the resource is not in the world.
+    pub fn is_borrowed<T: 'static>(&self) -> bool {
+        self.res
+            .get(&TypeId::of::<T>())
+            .is_some_and(|i| i.try_borrow_mut().is_err())
+    }
+
+    /// Returns the number of shared borrows of the resource `T` currently
+    /// alive, or `usize::MAX` while it is borrowed mutably, or `None` if the
+    /// resource is not in the world. This helps finding out why a `get_mut`
+    /// fails, without borrowing the resource.
+    ///
+    /// All borrows are counted, including the ones held by running systems
+    /// and by the `Dispatcher` while building its stages.
+    pub fn borrow_count<T: 'static>(&self) -> Option<usize> {
+        self.res.get(&TypeId::of::<T>()).map(|i| i.borrow_count())
+    }
+
+    /// Returns the number of guards extended by the `lock` of the built-in
+    /// systems that haven't been dropped yet. Only available in debug builds,
+    /// where the dispatchers check that it doesn't increase while building
+    /// their stages or in `Dispatcher::dry_run`, to catch leaked guards.
+    ///
+    /// Borrows taken with `get`, `get_mut` and the like aren't counted.
+    #[cfg(debug_assertions)]
+    pub fn outstanding_borrows(&self) -> usize {
+        self.lock_guards.load(std::sync::atomic::Ordering::SeqCst)
+    }
+
+    /// Formats a resource with `{:#?}`, borrowing it only while formatting.
+    /// Will return an error if the type is:
+    /// - Non initialized
+    /// - Already borrowed mutably
+    #[cfg_attr(feature = "debug_borrows", track_caller)]
+    pub fn resource_debug<T: std::fmt::Debug + Send + Sync + 'static>(
+        &self,
+    ) -> Result<String, EcsError> {
+        self.get::<T>().map(|i| format!("{:#?}", *i))
+    }
+
+    /// Borrows a resource mutably and runs the provided function on it,
+    /// returning its result.
+    /// The resource is released as soon as the function returns.
+    /// Will return an error if the type is:
+    /// - Non initialized
+    /// - Already borrowed immutably
+    /// - Already borrowed mutably
+    #[cfg_attr(feature = "debug_borrows", track_caller)]
+    pub fn get_mut_map<T: Send + Sync + 'static, R>(
+        &self,
+        f: impl FnOnce(&mut T) -> R,
+    ) -> Result<R, EcsError> {
+        self.get_mut::<T>().map(|mut i| f(&mut i))
+    }
+
+    /// Swaps the value of a resource with `value`, leaving the previous value
+    /// of the resource in `value`. For example, this lets a system holding
+    /// the back buffer of a double-buffered resource swap it with the front
+    /// one, since it only needs `&self`.
+    /// Will return an error and leave `value` untouched if the type is:
+    /// - Non initialized
+    /// - Already borrowed immutably
+    /// - Already borrowed mutably
+    #[cfg_attr(feature = "debug_borrows", track_caller)]
+    pub fn swap_value<T: Send + Sync + 'static>(&self, value: &mut T) -> Result<(), EcsError> {
+        self.get_mut::<T>()
+            .map(|mut i| std::mem::swap(&mut *i, value))
+    }
+
+    /// The C-ABI variant of `get_mut_map`, for embedders that can't hold a
+    /// guard across the API boundary. Borrows the resource mutably, calls `f`
+    /// with a pointer to it and the opaque `data` pointer, then releases it.
+    ///
+    /// Prefer `get_mut_map` from Rust. The pointer to the resource is only
+    /// valid until `f` returns, and must not be kept by foreign code.
+    /// Will return the same errors as `get_mut`, in which case `f` isn't called.
+    pub fn access_raw<T: Send + Sync + 'static>(
+        &self,
+        data: *mut std::ffi::c_void,
+        f: extern "C" fn(*mut T, *mut std::ffi::c_void),
+    ) -> Result<(), EcsError> {
+        self.get_mut_map(|value: &mut T| f(value, data))
+    }
+
+    /// Runs each update against its resource in turn, borrowing only one
+    /// resource at a time.
+    ///
+    /// Every update runs even if an earlier one fails. The result of each
+    /// update is returned in the same order, which is an error if its closure
+    /// failed or if its resource couldn't be borrowed, as with `get_mut`.
+    pub fn batch_mut<'a>(
+        &self,
+        updates: impl IntoIterator<Item = ResourceUpdate<'a>>,
+    ) -> Vec<SystemResult> {
+        updates
+            .into_iter()
+            .map(|update| (update.update)(self))
+            .collect()
+    }
+
+    /// The error returned when a resource can't be borrowed because of an
+    /// existing borrow.
+    #[cfg_attr(not(feature = "debug_borrows"), allow(unused_variables))]
+    pub(crate) fn already_borrowed(&self, typeid: &TypeId) -> EcsError {
+        #[cfg(feature = "debug_borrows")]
+        if let Some(borrower) = self
+            .res
+            .get(typeid)
+            .and_then(|i| i.borrowers.lock().unwrap().current())
+        {
+            return EcsError::AlreadyBorrowedBy(borrower);
+        }
+        EcsError::AlreadyBorrowed
     }
 
     /// Get a mutable reference to a resource by type, default-initializing it if not already
     /// initialized.
-    pub fn get_mut_or_default<T: Default + Send + Sync + 'static>(&mut self) -> AtomicRefMut<T> {
+    pub fn get_mut_or_default<T: Default + Send + Sync + 'static>(&mut self) -> ResMut<'_, T> {
         self.initialize::<T>();
         self.get_mut().unwrap()
     }
 
+    /// Initializes a resource if it is absent, then runs the provided function
+    /// on it. Useful during setup, for example to fill a collection resource.
+    pub fn modify<T: Default + Send + Sync + 'static>(&mut self, f: impl FnOnce(&mut T)) {
+        f(&mut self.get_mut_or_default::<T>())
+    }
+
+    /// Like `modify`, for types that don't implement `Default`: inserts
+    /// `default` if the resource is absent, then runs the provided function on
+    /// the stored value, whether it was just inserted or not.
+    pub fn modify_or_insert<T: Send + Sync + 'static>(
+        &mut self,
+        default: T,
+        f: impl FnOnce(&mut T),
+    ) {
+        self.insert_if_absent(default);
+        f(&mut self.get_mut::<T>().unwrap())
+    }
+
+    /// Get a mutable reference to a resource by type, inserting the value
+    /// returned by `f` if not already present.
+    ///
+    /// `f` is only called if the resource is absent. If it fails, the error is
+    /// returned and the world is left unchanged.
+    pub fn get_or_try_insert_with<T: Send + Sync + 'static, E>(
+        &mut self,
+        f: impl FnOnce() -> Result<T, E>,
+    ) -> Result<ResMut<'_, T>, E> {
+        if !self.res.contains_key(&TypeId::of::<T>()) {
+            self.insert_if_absent(f()?);
+        }
+        Ok(self.get_mut().unwrap())
+    }
+
+    /// Get a mutable reference to a resource by type, running `producer` to
+    /// compute it if not already present, for resources derived from others.
+    ///
+    /// The producer is a system returning the value of the resource, which is
+    /// then inserted. It shouldn't borrow `T` itself, as that would insert its
+    /// default value first. The resources of the producer are initialized
+    /// before it runs. If it fails, the error is returned and `T` isn't
+    /// inserted.
+    /// ```
+    /// use world_dispatcher::*;
+    ///
+    /// #[derive(Default)]
+    /// struct Total(u32);
+    ///
+    /// let mut world = World::default().with_resource(vec![1u32, 2, 3]);
+    /// let total = world
+    ///     .get_or_init(|values: &Vec<u32>| Ok(Total(values.iter().sum())))
+    ///     .unwrap();
+    /// assert_eq!(total.0, 6);
+    /// ```
+    pub fn get_or_init<T: Send + Sync + 'static, R>(
+        &mut self,
+        producer: impl IntoSystem<R, T>,
+    ) -> Result<ResMut<'_, T>, EcsError> {
+        if !self.res.contains_key(&TypeId::of::<T>()) {
+            let mut producer = producer.system();
+            producer.initialize(self);
+            let value = producer.run(self)?;
+            self.insert_if_absent(value);
+        }
+        Ok(self.get_mut().unwrap())
+    }
+
     /// Get a mutable reference to a resource by its type id. Useful if using
     /// dynamic dispatching.
     /// Will return an error if the type is:
@@ -66,17 +675,410 @@ impl World {
     pub fn get_by_typeid(
         &self,
         typeid: &TypeId,
-    ) -> Result<AtomicRefMut<Box<dyn Resource>>, EcsError> {
+    ) -> Result<ResMut<'_, Box<dyn Resource>>, EcsError> {
         self.res
             .get(typeid)
             .ok_or(EcsError::NotInitialized)
-            .and_then(|i| i.try_borrow_mut().map_err(|_| EcsError::AlreadyBorrowed))
+            .and_then(|i| {
+                i.try_borrow_mut()
+                    .map_err(|_| self.already_borrowed(typeid))
+            })
+            .map(|i| self.bump_tick_mut(typeid, i))
+    }
+
+    /// Resets the borrow state of every resource to unborrowed, recovering
+    /// from borrows that were leaked, for example with `std::mem::forget`.
+    ///
+    /// This is sound because taking `&mut self` proves that no borrow is
+    /// still alive. Borrows held by a panicking system are already released
+    /// while unwinding, so this is only needed for leaked borrows.
+    pub fn clear_borrows(&mut self) {
+        for res in self.res.values_mut() {
+            // Unsafe: the value is moved to a new cell, and the old cell is
+            // forgotten so that the value isn't dropped twice. `into_inner`
+            // can't be used, as it asserts that the cell isn't borrowed.
+            let value = unsafe { std::ptr::read(res.as_ptr()) };
+            std::mem::forget(std::mem::replace(res, ResourceCell::new(value)));
+        }
+    }
+
+    /// Removes a resource by its type id, returning the boxed value.
+    /// The boxed resource can be converted back to its type using `downcast`.
+    /// Will return an error and leave the world untouched if the type is:
+    /// - Non initialized
+    /// - Still borrowed, which can only happen if a borrow was leaked
+    pub fn take_boxed(&mut self, typeid: TypeId) -> Result<Box<dyn Resource>, EcsError> {
+        if self
+            .res
+            .get(&typeid)
+            .ok_or(EcsError::NotInitialized)?
+            .try_borrow_mut()
+            .is_err()
+        {
+            return Err(EcsError::AlreadyBorrowed);
+        }
+        self.names.remove(&typeid);
+        self.sizes.remove(&typeid);
+        self.ticks.remove(&typeid);
+        self.forget_change(&typeid);
+        self.storage_changed();
+        Ok(self.res.remove(&typeid).unwrap().into_inner())
+    }
+
+    /// Replaces a resource by the result of `f`, which takes ownership of the
+    /// current value, for transforms that need to consume it.
+    ///
+    /// Will return an error and leave the world untouched if the type is:
+    /// - Non initialized
+    /// - Still borrowed, which can only happen if a borrow was leaked
+    ///
+    /// The resource is removed while `f` runs, so it is lost if `f` panics.
+    pub fn map_resource<T: Send + Sync + 'static>(
+        &mut self,
+        f: impl FnOnce(T) -> T,
+    ) -> Result<(), EcsError> {
+        let value = self.take_boxed(TypeId::of::<T>())?;
+        let value = value
+            .downcast::<T>()
+            .unwrap_or_else(|_| unreachable!("resources are indexed by their type id"));
+        self.insert_if_absent(f(*value));
+        Ok(())
+    }
+
+    /// Removes all the resources from the world, returning them along with
+    /// their type name.
+    /// The boxed resources can be converted back to their type using
+    /// `downcast`.
+    ///
+    /// Will return an error and leave the world untouched if any resource
+    /// is still borrowed, which can only happen if a borrow was leaked.
+    pub fn drain_resources(
+        &mut self,
+    ) -> Result<impl Iterator<Item = (&'static str, Box<dyn Resource>)>, EcsError> {
+        if self.res.values().any(|i| i.try_borrow_mut().is_err()) {
+            return Err(EcsError::AlreadyBorrowed);
+        }
+        self.sizes.clear();
+        self.ticks.clear();
+        for id in self.res.keys() {
+            self.forget_change(id);
+        }
+        self.storage_changed();
+        let mut names = std::mem::take(&mut self.names);
+        Ok(std::mem::take(&mut self.res)
+            .into_iter()
+            .map(move |(id, i)| (names.remove(&id).unwrap(), i.into_inner())))
+    }
+
+    /// Registers the resource type `T` as being viewable as the trait object
+    /// `D`, using the provided cast (usually just `|t| t`).
+    ///
+    /// Registered resources can then be iterated over using `iter_mut_as`.
+    /// Registering the same type twice for the same trait does nothing.
+    pub fn register_as<T: Send + Sync + 'static, D: ?Sized + 'static>(
+        &mut self,
+        cast: fn(&mut T) -> &mut D,
+    ) {
+        let casts = self.casts.entry(TypeId::of::<D>()).or_default();
+        if casts.iter().any(|(id, _)| *id == TypeId::of::<T>()) {
+            return;
+        }
+        let cast: CastMut<D> = Box::new(move |res| cast(res.downcast_mut::<T>().unwrap()));
+        casts.push((TypeId::of::<T>(), Box::new(cast)));
+    }
+
+    /// Registers the resource type `T` as being viewable immutably as the
+    /// trait object `D`, using the provided cast (usually just `|t| t`).
+    ///
+    /// Registered resources can then be borrowed using `borrow_as`.
+    /// Registering the same type twice for the same trait does nothing.
+    pub fn register_dyn<T: Send + Sync + 'static, D: ?Sized + 'static>(
+        &mut self,
+        cast: fn(&T) -> &D,
+    ) {
+        let casts = self.ref_casts.entry(TypeId::of::<D>()).or_default();
+        if casts.iter().any(|(id, _)| *id == TypeId::of::<T>()) {
+            return;
+        }
+        casts.push((TypeId::of::<T>(), Box::new(cast)));
+    }
+
+    /// Get an immutable reference to the resource `T`, viewed as the trait
+    /// object `D` registered with `register_dyn`.
+    /// Will return an error if the type is:
+    /// - Not registered as `D`, as `EcsError::NotRegistered`
+    /// - Non initialized
+    /// - Already borrowed mutably
+    #[cfg_attr(feature = "debug_borrows", track_caller)]
+    pub fn borrow_as<T: Send + Sync + 'static, D: ?Sized + 'static>(
+        &self,
+    ) -> Result<Res<'_, D>, EcsError> {
+        let cast = *self
+            .ref_casts
+            .get(&TypeId::of::<D>())
+            .into_iter()
+            .flatten()
+            .find(|(id, _)| *id == TypeId::of::<T>())
+            .and_then(|(_, cast)| cast.downcast_ref::<fn(&T) -> &D>())
+            .ok_or(EcsError::NotRegistered {
+                type_name: Self::type_name::<T>(),
+                as_type: Self::type_name::<D>(),
+            })?;
+        self.get::<T>().map(|r| Res::map(r, cast))
+    }
+
+    /// Inserts the implementation of the trait object `D` held by the
+    /// resource `DynResource<D>`, replacing the previous one if any.
+    pub fn insert_dyn<D: ?Sized + Send + Sync + 'static>(&mut self, value: Box<D>) {
+        self.initialize::<DynResource<D>>();
+        self.get_mut::<DynResource<D>>().unwrap().set(value);
+    }
+
+    /// Inserts the resource `Shared<T>`, sharing `value` with the other
+    /// holders of the `Arc`, replacing the previous one if any. See `Shared`
+    /// for the locking rules.
+    pub fn insert_shared<T: Send + 'static>(&mut self, value: std::sync::Arc<Mutex<T>>) {
+        let value = Shared::new(value);
+        match self.res.get_mut(&TypeId::of::<Shared<T>>()) {
+            Some(res) => {
+                *res.get_mut() = Box::new(value);
+                self.forget_change(&TypeId::of::<Shared<T>>());
+                self.bump_tick(&TypeId::of::<Shared<T>>());
+            }
+            None => {
+                self.insert_if_absent(value);
+            }
+        }
+    }
+
+    /// Get a mutable reference to the implementation of the trait object `D`
+    /// inserted with `insert_dyn`.
+    /// Will return the same errors as `get_mut`, and `EcsError::NotInitialized`
+    /// if the `DynResource<D>` is empty.
+    #[cfg_attr(feature = "debug_borrows", track_caller)]
+    pub fn get_dyn_mut<D: ?Sized + Send + Sync + 'static>(
+        &self,
+    ) -> Result<ResMut<'_, D>, EcsError> {
+        let res = self.get_mut::<DynResource<D>>()?;
+        if !res.is_set() {
+            return Err(EcsError::NotInitialized);
+        }
+        Ok(ResMut::map(res, |res| &mut **res))
+    }
+
+    /// Iterates mutably over all resources registered as `D` using
+    /// `register_as`, in registration order.
+    /// Registered types that are not initialized are skipped.
+    /// Will yield an error for each resource that is already borrowed.
+    pub fn iter_mut_as<D: ?Sized + 'static>(
+        &self,
+    ) -> impl Iterator<Item = Result<ResMut<'_, D>, EcsError>> {
+        self.casts
+            .get(&TypeId::of::<D>())
+            .into_iter()
+            .flatten()
+            .filter_map(move |(id, cast)| {
+                let cast = cast.downcast_ref::<CastMut<D>>().unwrap();
+                self.res.get(id).map(|i| {
+                    i.try_borrow_mut()
+                        .map_err(|_| self.already_borrowed(id))
+                        .map(|i| {
+                            let i = self.bump_tick_mut(id, i);
+                            ResMut::map(i, |j| cast(j.as_mut()))
+                        })
+                })
+            })
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::*;
+    use std::borrow::Borrow;
+
+    #[test]
+    fn map_resource() {
+        let mut world = World::default();
+        assert!(matches!(
+            world.map_resource(|v: Vec<u32>| v),
+            Err(EcsError::NotInitialized)
+        ));
+        world.insert_if_absent(vec![3u32, 1, 2]);
+        world
+            .map_resource(|v: Vec<u32>| {
+                let mut v = v.into_iter().rev().collect::<Vec<_>>();
+                v.push(0);
+                v
+            })
+            .unwrap();
+        assert_eq!(*world.get::<Vec<u32>>().unwrap(), vec![2, 1, 3, 0]);
+        std::mem::forget(world.get::<Vec<u32>>().unwrap());
+        assert!(matches!(
+            world.map_resource(|v: Vec<u32>| v),
+            Err(EcsError::AlreadyBorrowed)
+        ));
+    }
+
+    #[test]
+    fn world_id() {
+        let world = World::default();
+        let other = World::default();
+        assert_eq!(world.id(), world.id());
+        assert_ne!(world.id(), other.id());
+    }
+
+    #[test]
+    fn clear_borrows() {
+        let mut world = World::default();
+        world.initialize::<u32>();
+        world.initialize::<u64>();
+        std::mem::forget(world.get_mut::<u32>().unwrap());
+        std::mem::forget(world.get::<u64>().unwrap());
+        assert!(world.get::<u32>().is_err());
+        assert!(world.get_mut::<u64>().is_err());
+        world.clear_borrows();
+        *world.get_mut::<u32>().unwrap() = 1;
+        *world.get_mut::<u64>().unwrap() = 2;
+        assert!(!world.is_borrowed::<u32>());
+        assert_eq!(*world.get::<u32>().unwrap(), 1);
+    }
+
+    #[test]
+    fn with_resource() {
+        let world = World::default()
+            .with_resource(1u32)
+            .with_resource(String::from("a"))
+            .with_resource(2u32);
+        assert_eq!(*world.get::<u32>().unwrap(), 2);
+        assert_eq!(*world.get::<String>().unwrap(), "a");
+        assert_eq!(world.resource_names().count(), 2);
+    }
+
+    #[test]
+    fn modify() {
+        let mut world = World::default();
+        world.modify(|v: &mut Vec<u32>| v.push(1));
+        world.modify(|v: &mut Vec<u32>| v.extend([2, 3]));
+        assert_eq!(*world.get::<Vec<u32>>().unwrap(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn modify_or_insert() {
+        struct Total(u32);
+        let mut world = World::default();
+        world.modify_or_insert(Total(10), |t| t.0 += 1);
+        assert_eq!(world.get::<Total>().unwrap().0, 11);
+        world.modify_or_insert(Total(10), |t| t.0 += 1);
+        assert_eq!(world.get::<Total>().unwrap().0, 12);
+    }
+
+    #[test]
+    fn resource_debug() {
+        #[derive(Debug, Default)]
+        struct Pos {
+            x: i32,
+        }
+        let mut world = World::default();
+        assert!(matches!(
+            world.resource_debug::<Pos>(),
+            Err(EcsError::NotInitialized)
+        ));
+        world.initialize::<Pos>();
+        world.get_mut::<Pos>().unwrap().x = 3;
+        assert_eq!(
+            world.resource_debug::<Pos>().unwrap(),
+            "Pos {\n    x: 3,\n}"
+        );
+        assert!(!world.is_borrowed::<Pos>());
+        let _long_borrow = world.get_mut::<Pos>().unwrap();
+        match world.resource_debug::<Pos>() {
+            Err(EcsError::AlreadyBorrowed) => {}
+            #[cfg(feature = "debug_borrows")]
+            Err(EcsError::AlreadyBorrowedBy(_)) => {}
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn batch_mut() {
+        let mut world = World::default();
+        world.initialize::<u32>();
+        world.initialize::<String>();
+        let mut log = vec![];
+        let updates = vec![
+            ResourceUpdate::new(|n: &mut u32| {
+                *n += 1;
+                Ok(())
+            }),
+            ResourceUpdate::new(|_: &mut u64| Ok(())),
+            ResourceUpdate::new(|s: &mut String| {
+                s.push('a');
+                Err(EcsError::AlreadyBorrowed)
+            }),
+            ResourceUpdate::new(|n: &mut u32| {
+                log.push(*n);
+                Ok(())
+            }),
+        ];
+        assert_eq!(updates[1].type_id(), TypeId::of::<u64>());
+        let results = world.batch_mut(updates);
+        assert!(results[0].is_ok());
+        assert!(matches!(results[1], Err(EcsError::NotInitialized)));
+        assert!(matches!(results[2], Err(EcsError::AlreadyBorrowed)));
+        assert!(results[3].is_ok());
+        assert_eq!(log, vec![1]);
+        assert_eq!(*world.get::<String>().unwrap(), "a");
+
+        let _long_borrow = world.get::<u32>().unwrap();
+        let results = world.batch_mut(vec![ResourceUpdate::new(|_: &mut u32| Ok(()))]);
+        match results[0] {
+            Err(EcsError::AlreadySharedBorrowed { .. }) => {}
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn guard_ergonomics() {
+        use std::borrow::BorrowMut;
+        fn len<S: AsRef<String>>(s: S) -> usize {
+            s.as_ref().len()
+        }
+        fn first<B: Borrow<Vec<u32>>>(b: B) -> u32 {
+            b.borrow()[0]
+        }
+        fn push<B: BorrowMut<Vec<u32>>>(mut b: B) {
+            b.borrow_mut().push(2);
+        }
+        fn clear<S: AsMut<String>>(mut s: S) {
+            s.as_mut().clear();
+        }
+        let mut world = World::default();
+        world.initialize::<String>();
+        world.initialize::<Vec<u32>>();
+        world.get_mut::<String>().unwrap().push_str("hello");
+        {
+            let mut guard = world.get_mut::<String>().unwrap();
+            guard.push('!');
+            assert_eq!(*guard, "hello!");
+            assert_eq!(format!("{:?} {}", guard, guard), "\"hello!\" hello!");
+            assert_eq!(len(guard), 6);
+        }
+        let guard = world.get::<String>().unwrap();
+        assert_eq!(format!("{:?} {}", guard, guard), "\"hello!\" hello!");
+        assert_eq!(len(guard), 6);
+        clear(world.get_mut::<String>().unwrap());
+        assert_eq!(world.get::<String>().unwrap().to_string(), "");
+
+        world.get_mut::<Vec<u32>>().unwrap().push(1);
+        push(world.get_mut::<Vec<u32>>().unwrap());
+        assert_eq!(first(world.get::<Vec<u32>>().unwrap()), 1);
+        assert_eq!(first(world.get_mut::<Vec<u32>>().unwrap()), 1);
+        let guard: &Vec<u32> = &world.get::<Vec<u32>>().unwrap();
+        assert_eq!(guard, &vec![1, 2]);
+        let first_value = Res::map(world.get::<Vec<u32>>().unwrap(), |v| &v[0]);
+        assert_eq!(*first_value, 1);
+    }
+
     #[test]
     fn init_borrow() {
         let mut world = World::default();
@@ -87,24 +1089,69 @@ mod tests {
             let _long_borrow = world.get::<u32>().unwrap();
             let _long_borrow2 = world.get::<u32>().unwrap();
             let failing_borrow = world.get_mut::<u32>();
-            if let EcsError::AlreadyBorrowed = failing_borrow.err().unwrap() {
-                // good
-            } else {
-                unreachable!();
+            match failing_borrow.err().unwrap() {
+                e @ EcsError::AlreadySharedBorrowed { .. } => assert_eq!(
+                    e.to_string(),
+                    "cannot borrow `u32` mutably because it is already shared-borrowed \
+                    (possibly by an enclosing scope)"
+                ),
+                _ => unreachable!(),
             }
         }
         {
             let _long_borrow = world.get_mut::<u32>().unwrap();
             let failing_borrow = world.get::<u32>();
-            if let EcsError::AlreadyBorrowed = failing_borrow.err().unwrap() {
-                // good
-            } else {
-                unreachable!();
+            match failing_borrow.err().unwrap() {
+                EcsError::AlreadyBorrowed => {}
+                #[cfg(feature = "debug_borrows")]
+                EcsError::AlreadyBorrowedBy(_) => {}
+                _ => unreachable!(),
             }
         }
         assert_eq!(*world.get_mut::<u32>().unwrap(), 6);
     }
 
+    #[test]
+    fn borrow_contract() {
+        let mut world = World::default();
+        world.initialize::<u32>();
+        world.initialize::<u64>();
+        let shared_ok = |world: &World| world.get::<u32>().is_ok();
+        let exclusive_ok = |world: &World| world.get_mut::<u32>().is_ok();
+        {
+            // Shared, then shared: allowed, any number of times.
+            let _a = world.get::<u32>().unwrap();
+            let _b = world.get::<u32>().unwrap();
+            assert!(shared_ok(&world));
+            // Shared, then exclusive: not allowed.
+            assert!(!exclusive_ok(&world));
+            assert!(world.try_get_mut::<u32>().is_none());
+            // Other types are unaffected.
+            assert!(world.get_mut::<u64>().is_ok());
+        }
+        {
+            // Dropping one of two shared guards still blocks exclusive borrows.
+            let a = world.get::<u32>().unwrap();
+            let b = world.get::<u32>().unwrap();
+            drop(a);
+            assert!(!exclusive_ok(&world));
+            drop(b);
+            assert!(exclusive_ok(&world));
+        }
+        {
+            // Exclusive, then shared or exclusive: not allowed.
+            let _a = world.get_mut::<u32>().unwrap();
+            assert!(!shared_ok(&world));
+            assert!(!exclusive_ok(&world));
+            assert!(world.try_get::<u32>().is_none());
+            assert!(world.get::<u64>().is_ok());
+        }
+        // Every pattern works again once all the guards are dropped.
+        assert!(shared_ok(&world));
+        assert!(exclusive_ok(&world));
+        assert!(!world.is_borrowed::<u32>());
+    }
+
     #[test]
     fn init_or_default() {
         let mut world = World::default();
@@ -112,4 +1159,681 @@ mod tests {
         let mut data = world.get_mut_or_default::<u32>();
         *data += 1;
     }
+
+    #[test]
+    fn get_shared_recursive() {
+        fn nested(world: &World) -> u32 {
+            *world.get_shared_recursive::<u32>().unwrap()
+        }
+        let mut world = World::default();
+        world.initialize::<u32>();
+        *world.get_mut::<u32>().unwrap() = 3;
+        {
+            // Shared, then shared again: allowed.
+            let outer = world.get_shared_recursive::<u32>().unwrap();
+            assert_eq!(nested(&world), *outer);
+            let inner = world.get::<u32>().unwrap();
+            assert_eq!(*inner, 3);
+            // Shared, then exclusive: not allowed.
+            assert!(world.get_mut::<u32>().is_err());
+        }
+        {
+            // Exclusive, then shared: not allowed.
+            let _w = world.get_mut::<u32>().unwrap();
+            assert!(world.get_shared_recursive::<u32>().is_err());
+        }
+        // Once all guards are dropped, exclusive access works again.
+        assert!(world.get_mut::<u32>().is_ok());
+    }
+
+    #[test]
+    fn type_names_match() {
+        let mut world = World::default();
+        world.initialize::<u32>();
+        let names = world.resource_names().collect::<Vec<_>>();
+        assert_eq!(names, vec![World::type_name::<u32>()]);
+        let missing = match world.get_many_refs::<(u32, i8)>() {
+            Err(EcsError::ResourceNotFound { type_name }) => type_name,
+            _ => unreachable!(),
+        };
+        assert_eq!(missing, World::type_name::<i8>());
+        world.initialize::<i8>();
+        assert!(world.resource_names().any(|name| name == missing));
+        let conflicting = match world.get_ref_and_mut::<u32, u32>() {
+            Err(EcsError::ConflictingAccess { type_name }) => type_name,
+            _ => unreachable!(),
+        };
+        assert!(world.resource_names().any(|name| name == conflicting));
+    }
+
+    #[test]
+    fn get_or_try_insert_with() {
+        let mut world = World::default();
+        assert!(world.get_or_try_insert_with(|| "x".parse::<u32>()).is_err());
+        assert!(world.get::<u32>().is_err());
+        assert_eq!(world.resource_names().count(), 0);
+        let value = world.get_or_try_insert_with(|| "5".parse::<u32>()).unwrap();
+        assert_eq!(*value, 5);
+        drop(value);
+        let value = world
+            .get_or_try_insert_with::<u32, ()>(|| unreachable!())
+            .unwrap();
+        assert_eq!(*value, 5);
+    }
+
+    #[test]
+    fn resource_size_hint() {
+        let mut world = World::default();
+        world.initialize::<u32>();
+        world.insert_if_absent([0u8; 100]);
+        world.get_mut_or_default::<Vec<u64>>().extend(0..1000);
+        let mut sizes = world.resource_size_hint();
+        sizes.sort_unstable();
+        let mut expected = vec![
+            (World::type_name::<u32>(), 4),
+            (World::type_name::<[u8; 100]>(), 100),
+            (
+                World::type_name::<Vec<u64>>(),
+                std::mem::size_of::<Vec<u64>>(),
+            ),
+        ];
+        expected.sort_unstable();
+        assert_eq!(sizes, expected);
+        let _borrowed = world.get_mut::<u32>().unwrap();
+        assert_eq!(world.resource_size_hint().len(), 3);
+    }
+
+    #[test]
+    fn insert_boxed_checked() {
+        let mut world = World::default();
+        let boxed: Box<dyn Resource> = Box::new(5u32);
+        world.insert_boxed_checked(boxed).unwrap();
+        assert_eq!(*world.get::<u32>().unwrap(), 5);
+        assert_eq!(
+            world.resource_names().collect::<Vec<_>>(),
+            vec![World::type_name::<u32>()]
+        );
+        assert_eq!(
+            world.resource_size_hint(),
+            vec![(World::type_name::<u32>(), 4)]
+        );
+        match world.insert_boxed_checked(Box::new(6u32)) {
+            Err(BoxedError::AlreadyPresent(value)) => {
+                assert_eq!(*value.downcast::<u32>().ok().unwrap(), 6)
+            }
+            _ => unreachable!(),
+        }
+        assert_eq!(*world.get::<u32>().unwrap(), 5);
+    }
+
+    #[test]
+    fn expect() {
+        let mut world = World::default();
+        world.initialize::<u32>();
+        *world.expect_mut::<u32>() = 3;
+        assert_eq!(*world.expect::<u32>(), 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "Resource `u64` is not in the world. Available resources: [\"u32\"]")]
+    fn expect_missing() {
+        let mut world = World::default();
+        world.initialize::<u32>();
+        world.expect::<u64>();
+    }
+
+    #[test]
+    #[should_panic(expected = "Resource `u32` cannot be borrowed: AlreadySharedBorrowed")]
+    fn expect_borrowed() {
+        let mut world = World::default();
+        world.initialize::<u32>();
+        let _r = world.get::<u32>().unwrap();
+        world.expect_mut::<u32>();
+    }
+
+    #[test]
+    fn take_boxed() {
+        let mut world = World::default();
+        world.insert_if_absent(5u32);
+        world.initialize::<u64>();
+        std::mem::forget(world.get::<u64>().unwrap());
+        assert!(matches!(
+            world.take_boxed(TypeId::of::<u64>()),
+            Err(EcsError::AlreadyBorrowed)
+        ));
+        assert!(world.get_mut::<u32>().is_ok());
+        let boxed = world.take_boxed(TypeId::of::<u32>()).unwrap();
+        assert_eq!(*boxed.downcast::<u32>().ok().unwrap(), 5);
+        assert!(matches!(world.get::<u32>(), Err(EcsError::NotInitialized)));
+        assert!(matches!(
+            world.take_boxed(TypeId::of::<u32>()),
+            Err(EcsError::NotInitialized)
+        ));
+        assert_eq!(
+            world.resource_names().collect::<Vec<_>>(),
+            vec![World::type_name::<u64>()]
+        );
+    }
+
+    #[test]
+    fn is_borrowed() {
+        let mut world = World::default();
+        assert!(!world.is_borrowed::<u32>());
+        world.initialize::<u32>();
+        assert!(!world.is_borrowed::<u32>());
+        {
+            let _r = world.get::<u32>().unwrap();
+            assert!(world.is_borrowed::<u32>());
+            assert!(world.get::<u32>().is_ok());
+        }
+        {
+            let _w = world.get_mut::<u32>().unwrap();
+            assert!(world.is_borrowed::<u32>());
+        }
+        assert!(!world.is_borrowed::<u32>());
+        assert!(world.get_mut::<u32>().is_ok());
+    }
+
+    #[test]
+    fn borrow_count() {
+        let mut world = World::default();
+        assert_eq!(world.borrow_count::<u32>(), None);
+        world.initialize::<u32>();
+        world.initialize::<Vec<u32>>();
+        assert_eq!(world.borrow_count::<u32>(), Some(0));
+        {
+            let _a = world.get::<u32>().unwrap();
+            let _b = world.get::<u32>().unwrap();
+            assert_eq!(world.borrow_count::<u32>(), Some(2));
+            assert!(world.get_mut::<u32>().is_err());
+            assert_eq!(world.borrow_count::<u32>(), Some(2));
+        }
+        {
+            let _w = world.get_mut::<u32>().unwrap();
+            assert_eq!(world.borrow_count::<u32>(), Some(usize::MAX));
+            assert!(world.get::<u32>().is_err());
+        }
+        assert_eq!(world.borrow_count::<u32>(), Some(0));
+        {
+            let _slice = world.get_slice::<u32>().unwrap();
+            let _all = world
+                .borrow_all_shared(&[(TypeId::of::<Vec<u32>>(), "Vec<u32>")])
+                .unwrap();
+            assert_eq!(world.borrow_count::<Vec<u32>>(), Some(2));
+        }
+        assert_eq!(world.borrow_count::<Vec<u32>>(), Some(0));
+        std::mem::forget(world.get::<u32>().unwrap());
+        assert_eq!(world.borrow_count::<u32>(), Some(1));
+        world.clear_borrows();
+        assert_eq!(world.borrow_count::<u32>(), Some(0));
+    }
+
+    #[test]
+    fn insert_if_absent() {
+        let mut world = World::default();
+        assert!(world.insert_if_absent(5u32));
+        assert!(!world.insert_if_absent(6u32));
+        assert_eq!(*world.get::<u32>().unwrap(), 5);
+        world.initialize::<u32>();
+        assert_eq!(*world.get::<u32>().unwrap(), 5);
+    }
+
+    #[test]
+    fn swap_value() {
+        let mut world = World::default();
+        world.insert_if_absent(vec![1u32, 2]);
+        let mut back = vec![3u32];
+        world.swap_value(&mut back).unwrap();
+        assert_eq!(back, vec![1, 2]);
+        assert_eq!(*world.get::<Vec<u32>>().unwrap(), vec![3]);
+        back.clear();
+        world.swap_value(&mut back).unwrap();
+        assert_eq!(back, vec![3]);
+        assert!(world.get::<Vec<u32>>().unwrap().is_empty());
+        assert!(world.swap_value(&mut 0u8).is_err());
+        let _long_borrow = world.get::<Vec<u32>>().unwrap();
+        assert!(world.swap_value(&mut back).is_err());
+        assert_eq!(back, vec![3]);
+    }
+
+    #[test]
+    fn insert_shared() {
+        use std::sync::Arc;
+        fn add(total: &Shared<u32>) -> SystemResult {
+            *total.lock() += 1;
+            Ok(())
+        }
+        let total = Arc::new(Mutex::new(0u32));
+        let mut first = World::default();
+        let mut second = World::default();
+        first.insert_shared(total.clone());
+        second.insert_shared(total.clone());
+        let mut dispatch = DispatcherBuilder::new().add(add).build(&mut first);
+        let mut other = DispatcherBuilder::new()
+            .add(add)
+            .add(add)
+            .build(&mut second);
+        dispatch.run(&mut first).unwrap();
+        other.run(&mut second).unwrap();
+        assert_eq!(*total.lock().unwrap(), 3);
+        assert!(Arc::ptr_eq(
+            &first.get::<Shared<u32>>().unwrap().value(),
+            &total
+        ));
+
+        // Without `insert_shared`, the world has its own value.
+        let mut world = World::default();
+        DispatcherBuilder::new()
+            .add(add)
+            .build(&mut world)
+            .run(&mut world)
+            .unwrap();
+        assert_eq!(*world.get::<Shared<u32>>().unwrap().lock(), 1);
+        assert_eq!(*total.lock().unwrap(), 3);
+    }
+
+    #[test]
+    fn get_slice() {
+        fn double(world: &World) -> SystemResult {
+            for value in world.get_slice_mut::<u32>()?.iter_mut() {
+                *value *= 2;
+            }
+            Ok(())
+        }
+        fn sum(world: &World) -> SystemResult {
+            let total = world.get_slice::<u32>()?.iter().sum();
+            *world.get_mut::<u32>()? = total;
+            Ok(())
+        }
+        let mut world = World::default();
+        assert!(world.get_slice::<u32>().is_err());
+        world.initialize::<Vec<u32>>();
+        world.get_mut::<Vec<u32>>().unwrap().extend([1, 2, 3]);
+        let mut dispatch = DispatcherBuilder::new()
+            .add(double)
+            .add(sum)
+            .build(&mut world);
+        world.initialize::<u32>();
+        dispatch.run_seq(&world).unwrap();
+        assert_eq!(*world.get::<u32>().unwrap(), 12);
+        assert_eq!(&*world.get_slice::<u32>().unwrap(), &[2, 4, 6]);
+        {
+            let mut values = world.get_slice_mut::<u32>().unwrap();
+            values.reverse();
+            assert!(world.get_slice::<u32>().is_err());
+        }
+        assert_eq!(&*world.get_slice::<u32>().unwrap(), &[6, 4, 2]);
+    }
+
+    #[test]
+    fn get_mut_map() {
+        let mut world = World::default();
+        world.initialize::<Vec<u32>>();
+        world.get_mut::<Vec<u32>>().unwrap().push(3);
+        assert_eq!(
+            world.get_mut_map(|v: &mut Vec<u32>| v.pop()).unwrap(),
+            Some(3)
+        );
+        assert_eq!(world.get_mut_map(|v: &mut Vec<u32>| v.pop()).unwrap(), None);
+        assert!(world.get_mut::<Vec<u32>>().is_ok());
+        assert!(world.get_mut_map(|_: &mut u32| ()).is_err());
+        let _long_borrow = world.get::<Vec<u32>>().unwrap();
+        assert!(world.get_mut_map(|v: &mut Vec<u32>| v.pop()).is_err());
+    }
+
+    #[test]
+    fn access_raw() {
+        extern "C" fn double(value: *mut u32, calls: *mut std::ffi::c_void) {
+            unsafe {
+                *value *= 2;
+                *(calls as *mut u32) += 1;
+            }
+        }
+        extern "C" fn count(_: *mut u64, calls: *mut std::ffi::c_void) {
+            unsafe { *(calls as *mut u32) += 1 }
+        }
+        let world = World::default().with_resource(3u32);
+        let mut calls = 0u32;
+        let data = &mut calls as *mut u32 as *mut std::ffi::c_void;
+        world.access_raw(data, double).unwrap();
+        assert_eq!(*world.get::<u32>().unwrap(), 6);
+        assert!(!world.is_borrowed::<u32>());
+        let _long_borrow = world.get::<u32>().unwrap();
+        assert!(world.access_raw(data, double).is_err());
+        assert!(world.access_raw(data, count).is_err());
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(
+        expected = "Cannot insert `u64` into the `World` while `u32` is borrowed, \
+        as inserting may move the borrowed resources."
+    )]
+    fn insert_while_borrowed() {
+        let mut world = World::default().with_resource(3u32);
+        std::mem::forget(world.get::<u32>().unwrap());
+        world.initialize::<u32>();
+        world.initialize::<u64>();
+    }
+
+    #[test]
+    fn get_or_init() {
+        #[derive(Default)]
+        struct Total(u32);
+        let mut world = World::default();
+        assert!(world
+            .get_or_init(|values: &Vec<u32>| {
+                ensure!(!values.is_empty(), "no values");
+                Ok(Total(values.iter().sum()))
+            })
+            .is_err());
+        assert!(world.get::<Total>().is_err());
+        world.get_mut::<Vec<u32>>().unwrap().push(2);
+        assert_eq!(
+            world
+                .get_or_init(|values: &Vec<u32>| Ok(Total(values.iter().sum())))
+                .unwrap()
+                .0,
+            2
+        );
+        world.get_mut::<Vec<u32>>().unwrap().push(3);
+        let total = world
+            .get_or_init(|_: &Vec<u32>| -> SystemResult<Total> { unreachable!() })
+            .unwrap();
+        assert_eq!(total.0, 2);
+    }
+
+    #[test]
+    #[cfg(feature = "derive")]
+    fn derive_resource() {
+        #[derive(Resource)]
+        #[resource(default)]
+        struct Config {
+            name: String,
+            retries: u32,
+        }
+        let mut world = World::default();
+        world.initialize::<Config>();
+        world.get_mut::<Config>().unwrap().retries += 1;
+        let config = world.get::<Config>().unwrap();
+        assert_eq!((config.name.as_str(), config.retries), ("", 1));
+    }
+
+    #[test]
+    fn reset() {
+        let mut world = World::default().with_resource(vec![1u32]);
+        assert!(!world.reset::<Vec<u32>>());
+        assert_eq!(world.get::<Vec<u32>>().unwrap().len(), 1);
+
+        world.initialize::<Vec<u32>>();
+        let mut world = world.with_resource(vec![2u32, 3]);
+        assert!(world.reset::<Vec<u32>>());
+        assert!(world.get::<Vec<u32>>().unwrap().is_empty());
+
+        *world.get_mut::<Vec<u32>>().unwrap() = vec![4];
+        world.take_boxed(TypeId::of::<Vec<u32>>()).unwrap();
+        assert!(world.reset::<Vec<u32>>());
+        assert!(world.get::<Vec<u32>>().unwrap().is_empty());
+    }
+
+    #[test]
+    fn try_get() {
+        let world = World::default();
+        assert!(world.try_get::<u32>().is_none());
+        assert!(world.try_get_mut::<u32>().is_none());
+        let world = world.with_resource(3u32);
+        *world.try_get_mut::<u32>().unwrap() += 1;
+        assert_eq!(*world.try_get::<u32>().unwrap(), 4);
+        {
+            let _long_borrow = world.get::<u32>().unwrap();
+            assert!(world.try_get::<u32>().is_some());
+            assert!(world.try_get_mut::<u32>().is_none());
+        }
+        let _long_borrow = world.get_mut::<u32>().unwrap();
+        assert!(world.try_get::<u32>().is_none());
+        assert!(world.try_get_mut::<u32>().is_none());
+    }
+
+    #[test]
+    fn get_ref_and_mut() {
+        let mut world = World::default();
+        world.initialize::<u32>();
+        world.initialize::<u64>();
+        {
+            let (a, mut b) = world.get_ref_and_mut::<u32, u64>().unwrap();
+            *b = *a as u64 + 1;
+        }
+        assert_eq!(*world.get::<u64>().unwrap(), 1);
+        match world.get_ref_and_mut::<u32, u32>() {
+            Err(EcsError::ConflictingAccess { type_name }) => assert_eq!(type_name, "u32"),
+            _ => unreachable!(),
+        }
+        match world.get_ref_and_mut::<u32, i8>() {
+            Err(EcsError::ResourceNotFound { type_name }) => assert_eq!(type_name, "i8"),
+            _ => unreachable!(),
+        }
+        assert!(world.get_mut::<u32>().is_ok());
+    }
+
+    #[test]
+    fn extend_boxed() {
+        let mut source = World::default().with_resource(5u32).with_resource(6u64);
+        let mut world = World::default().with_resource(1u32).with_resource(2i8);
+        world.extend_boxed(source.drain_resources().unwrap().map(|(_, value)| value));
+        assert_eq!(*world.get::<u32>().unwrap(), 5);
+        assert_eq!(*world.get::<u64>().unwrap(), 6);
+        assert_eq!(*world.get::<i8>().unwrap(), 2);
+        assert!(world.resource_names().any(|name| name == "u64"));
+        assert!(source.get::<u32>().is_err());
+    }
+
+    #[test]
+    fn drain_resources() {
+        let mut world = World::default();
+        world.initialize::<u32>();
+        world.initialize::<u64>();
+        *world.get_mut::<u32>().unwrap() = 5;
+        std::mem::forget(world.get::<u64>().unwrap());
+        assert!(world.drain_resources().is_err());
+        assert!(world.get::<u32>().is_ok());
+
+        let mut world = World::default();
+        world.initialize::<u32>();
+        world.initialize::<u64>();
+        *world.get_mut::<u32>().unwrap() = 5;
+        let mut drained = world.drain_resources().unwrap().collect::<Vec<_>>();
+        drained.sort_by_key(|(name, _)| *name);
+        assert_eq!(drained.len(), 2);
+        assert_eq!(drained[0].0, "u32");
+        assert_eq!(drained[1].0, "u64");
+        let (_, value) = drained.remove(0);
+        assert_eq!(*value.downcast::<u32>().ok().unwrap(), 5);
+        assert!(world.get::<u32>().is_err());
+        assert!(world.drain_resources().unwrap().next().is_none());
+    }
+
+    #[test]
+    fn borrow_as_trait() {
+        trait Describe {
+            fn describe(&self) -> String;
+        }
+        #[derive(Default)]
+        struct A(u32);
+        impl Describe for A {
+            fn describe(&self) -> String {
+                format!("A({})", self.0)
+            }
+        }
+        let mut world = World::default();
+        world.register_dyn::<A, dyn Describe>(|a| a);
+        assert!(matches!(
+            world.borrow_as::<A, dyn Describe>(),
+            Err(EcsError::NotInitialized)
+        ));
+        world.initialize::<A>();
+        world.get_mut::<A>().unwrap().0 = 3;
+        {
+            let first = world.borrow_as::<A, dyn Describe>().unwrap();
+            let second = world.borrow_as::<A, dyn Describe>().unwrap();
+            assert_eq!(first.describe(), "A(3)");
+            assert_eq!(second.describe(), "A(3)");
+            assert!(world.get_mut::<A>().is_err());
+        }
+        match world.borrow_as::<A, dyn std::fmt::Debug>() {
+            Err(EcsError::NotRegistered { type_name, .. }) => {
+                assert_eq!(type_name, World::type_name::<A>())
+            }
+            _ => unreachable!(),
+        }
+        let _long_borrow = world.get_mut::<A>().unwrap();
+        assert!(world.borrow_as::<A, dyn Describe>().is_err());
+    }
+
+    #[test]
+    fn dyn_resource() {
+        trait Strategy: Send + Sync {
+            fn apply(&mut self, value: u32) -> u32;
+        }
+        struct Add(u32);
+        impl Strategy for Add {
+            fn apply(&mut self, value: u32) -> u32 {
+                value + self.0
+            }
+        }
+        struct Double;
+        impl Strategy for Double {
+            fn apply(&mut self, value: u32) -> u32 {
+                value * 2
+            }
+        }
+        fn step(strategy: &mut DynResource<dyn Strategy>, value: &mut u32) -> SystemResult {
+            *value = strategy.apply(*value);
+            Ok(())
+        }
+        let mut world = World::default();
+        let mut dispatcher = DispatcherBuilder::new().add(step).build(&mut world);
+        assert!(matches!(
+            world.get_dyn_mut::<dyn Strategy>(),
+            Err(EcsError::NotInitialized)
+        ));
+        world.insert_dyn::<dyn Strategy>(Box::new(Add(3)));
+        dispatcher.run_seq(&world).unwrap();
+        assert_eq!(*world.get::<u32>().unwrap(), 3);
+        world.insert_dyn::<dyn Strategy>(Box::new(Double));
+        dispatcher.run_seq(&world).unwrap();
+        assert_eq!(*world.get::<u32>().unwrap(), 6);
+        assert_eq!(world.get_dyn_mut::<dyn Strategy>().unwrap().apply(1), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "No implementation of `dyn")]
+    fn dyn_resource_empty() {
+        trait Strategy: Send + Sync {
+            fn apply(&self);
+        }
+        DynResource::<dyn Strategy>::default().apply();
+    }
+
+    #[test]
+    fn iter_mut_as_trait() {
+        trait Update {
+            fn update(&mut self);
+        }
+        #[derive(Default)]
+        struct A(u32);
+        #[derive(Default)]
+        struct B(u32);
+        impl Update for A {
+            fn update(&mut self) {
+                self.0 += 1;
+            }
+        }
+        impl Update for B {
+            fn update(&mut self) {
+                self.0 += 10;
+            }
+        }
+        let mut world = World::default();
+        world.initialize::<A>();
+        world.initialize::<B>();
+        world.register_as::<A, dyn Update>(|a| a);
+        world.register_as::<B, dyn Update>(|b| b);
+        world.register_as::<B, dyn Update>(|b| b);
+        // Registered but not initialized, skipped.
+        world.register_as::<u32, dyn std::fmt::Debug>(|x| x);
+        assert_eq!(world.iter_mut_as::<dyn std::fmt::Debug>().count(), 0);
+
+        for res in world.iter_mut_as::<dyn Update>() {
+            res.unwrap().update();
+        }
+        assert_eq!(world.get::<A>().unwrap().0, 1);
+        assert_eq!(world.get::<B>().unwrap().0, 10);
+
+        let _borrow = world.get::<A>().unwrap();
+        let results = world.iter_mut_as::<dyn Update>().collect::<Vec<_>>();
+        assert_eq!(results.len(), 2);
+        #[cfg(not(feature = "debug_borrows"))]
+        assert!(matches!(results[0], Err(EcsError::AlreadyBorrowed)));
+        #[cfg(feature = "debug_borrows")]
+        assert!(matches!(results[0], Err(EcsError::AlreadyBorrowedBy(_))));
+        assert!(results[1].is_ok());
+    }
+
+    #[cfg(feature = "debug_borrows")]
+    #[test]
+    fn debug_borrows_report_borrower() {
+        let mut world = World::default();
+        world.initialize::<u32>();
+        let line = line!() + 1;
+        let _long_borrow = world.get_mut::<u32>().unwrap();
+        match world.get::<u32>() {
+            Err(EcsError::AlreadyBorrowedBy(borrower)) => {
+                assert_eq!(borrower.system, None);
+                assert_eq!(borrower.location.line(), line);
+                assert_eq!(borrower.location.file(), file!());
+            }
+            _ => unreachable!(),
+        }
+        drop(_long_borrow);
+
+        let line = line!() + 1;
+        let _long_borrow = world.get::<u32>().unwrap();
+        drop(world.get::<u32>().unwrap());
+        match world.get_by_typeid(&TypeId::of::<u32>()) {
+            Err(EcsError::AlreadyBorrowedBy(borrower)) => {
+                assert_eq!(borrower.location.line(), line);
+            }
+            _ => unreachable!(),
+        }
+        drop(_long_borrow);
+
+        let _dispatch = DispatcherBuilder::new()
+            .add(|_: &mut u32| Ok(()))
+            .build(&mut world);
+        let _long_borrow = world.get_by_typeid(&TypeId::of::<u32>()).unwrap();
+        assert!(matches!(world.get::<u32>(), Err(EcsError::AlreadyBorrowed)));
+        drop(_long_borrow);
+
+        let mut sys = System {
+            initialize: std::sync::Arc::new(|_| {}),
+            lock: std::sync::Arc::new(|_, _| Ok(())),
+            run_fn: Box::new(|world| {
+                let _long_borrow = world.get_mut::<u32>()?;
+                match world.get::<u32>() {
+                    Err(EcsError::AlreadyBorrowedBy(borrower)) => {
+                        assert_eq!(borrower.system, Some("leaky_system"));
+                    }
+                    _ => unreachable!(),
+                }
+                Ok(())
+            }),
+            run_factory: None,
+            name: "leaky_system",
+            access: Access::default(),
+            label: None,
+            main_thread: false,
+        };
+        sys.run(&world).unwrap();
+    }
 }
@@ -0,0 +1,13 @@
+// The messages depend on the implementations of `IntoSystem`, which change
+// with these features.
+#[cfg(not(any(
+    feature = "big_systems",
+    feature = "flexible_ordering",
+    feature = "sync_systems",
+    feature = "anyhow"
+)))]
+#[test]
+fn compile_errors() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,5 @@
+use world_dispatcher::*;
+
+fn main() {
+    let _ = DispatcherBuilder::new().add(|_a: &mut u32, _b: &u64| Ok(()));
+}
@@ -0,0 +1,11 @@
+use world_dispatcher::*;
+
+struct NoDefault;
+
+fn sys(_a: &NoDefault) -> SystemResult {
+    Ok(())
+}
+
+fn main() {
+    let _ = DispatcherBuilder::new().add(sys);
+}
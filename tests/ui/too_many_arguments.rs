@@ -0,0 +1,13 @@
+use world_dispatcher::*;
+
+#[allow(clippy::too_many_arguments)]
+fn sys(
+    _: &u8, _: &u8, _: &u8, _: &u8, _: &u8, _: &u8, _: &u8,
+    _: &u8, _: &u8, _: &u8, _: &u8, _: &u8, _: &u8,
+) -> SystemResult {
+    Ok(())
+}
+
+fn main() {
+    let _ = DispatcherBuilder::new().add(sys);
+}
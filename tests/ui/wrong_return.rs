@@ -0,0 +1,7 @@
+use world_dispatcher::*;
+
+fn sys(_a: &u32) {}
+
+fn main() {
+    let _ = DispatcherBuilder::new().add(sys);
+}
@@ -0,0 +1,107 @@
+//! Provides `#[derive(Resource)]`, re-exported by `world_dispatcher` with its
+//! `derive` feature.
+
+use proc_macro::TokenStream;
+use quote::{quote, quote_spanned};
+use syn::spanned::Spanned;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields};
+
+/// Checks that a type can be used as a resource, meaning that it is
+/// `Send + Sync + 'static`, reporting the fields which aren't.
+///
+/// All such types are already resources, so this only improves the error
+/// messages, which otherwise show up where the type is first used as a
+/// resource. With `#[resource(default)]`, `Default` is also implemented for
+/// structs, using the default value of each field.
+///
+/// Generic types aren't supported, as whether they are resources depends on
+/// their parameters.
+#[proc_macro_derive(Resource, attributes(resource))]
+pub fn derive_resource(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> Result<proc_macro2::TokenStream, Error> {
+    if !input.generics.params.is_empty() {
+        return Err(Error::new(
+            input.generics.span(),
+            "`#[derive(Resource)]` doesn't support generic types",
+        ));
+    }
+    let mut default = None;
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("resource")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("default") {
+                default = Some(meta.path.span());
+                Ok(())
+            } else {
+                Err(meta.error("expected `default`"))
+            }
+        })?;
+    }
+
+    let name = &input.ident;
+    let fields: Vec<&Fields> = match &input.data {
+        Data::Struct(data) => vec![&data.fields],
+        Data::Enum(data) => data.variants.iter().map(|v| &v.fields).collect(),
+        Data::Union(data) => {
+            return Err(Error::new(
+                data.union_token.span,
+                "`#[derive(Resource)]` doesn't support unions",
+            ))
+        }
+    };
+    let assertions = fields.iter().flat_map(|fields| fields.iter()).map(|field| {
+        let ty = &field.ty;
+        quote_spanned! {ty.span()=> assert_resource::<#ty>();}
+    });
+
+    let default_impl = if let Some(span) = default {
+        let fields = match &input.data {
+            Data::Struct(data) => &data.fields,
+            _ => {
+                return Err(Error::new(
+                    span,
+                    "`#[resource(default)]` is only supported on structs",
+                ))
+            }
+        };
+        let body = match fields {
+            Fields::Named(fields) => {
+                let names = fields.named.iter().map(|f| &f.ident);
+                quote!(Self { #(#names: ::core::default::Default::default(),)* })
+            }
+            Fields::Unnamed(fields) => {
+                let values = fields
+                    .unnamed
+                    .iter()
+                    .map(|_| quote!(::core::default::Default::default()));
+                quote!(Self(#(#values,)*))
+            }
+            Fields::Unit => quote!(Self),
+        };
+        quote! {
+            impl ::core::default::Default for #name {
+                fn default() -> Self {
+                    #body
+                }
+            }
+        }
+    } else {
+        quote!()
+    };
+
+    Ok(quote! {
+        const _: () = {
+            fn assert_resource<T: ?Sized + Send + Sync + 'static>() {}
+            #[allow(dead_code)]
+            fn assert_fields() {
+                #(#assertions)*
+            }
+        };
+        #default_impl
+    })
+}
@@ -0,0 +1,35 @@
+use world_dispatcher_derive::Resource;
+
+#[derive(Resource)]
+#[resource(default)]
+struct Named {
+    count: u32,
+    names: Vec<String>,
+}
+
+#[derive(Resource)]
+#[resource(default)]
+struct Tuple(u32, Option<u64>);
+
+#[derive(Resource)]
+#[allow(dead_code)]
+enum State {
+    Idle,
+    Running { since: u64 },
+}
+
+#[test]
+fn derive_default() {
+    let named = Named::default();
+    assert_eq!(named.count, 0);
+    assert!(named.names.is_empty());
+    let tuple = Tuple::default();
+    assert_eq!((tuple.0, tuple.1), (0, None));
+    let _ = State::Idle;
+}
+
+#[test]
+fn compile_errors() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,13 @@
+use world_dispatcher_derive::Resource;
+
+#[derive(Resource)]
+#[resource(clone)]
+struct Config(u32);
+
+#[derive(Resource)]
+#[resource(default)]
+enum Mode {
+    Fast,
+}
+
+fn main() {}
@@ -0,0 +1,6 @@
+use world_dispatcher_derive::Resource;
+
+#[derive(Resource)]
+struct Wrapper<T>(T);
+
+fn main() {}
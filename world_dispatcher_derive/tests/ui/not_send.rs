@@ -0,0 +1,10 @@
+use std::rc::Rc;
+use world_dispatcher_derive::Resource;
+
+#[derive(Resource)]
+struct Shared {
+    count: u32,
+    value: Rc<u32>,
+}
+
+fn main() {}
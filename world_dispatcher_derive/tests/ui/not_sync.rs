@@ -0,0 +1,10 @@
+use std::cell::Cell;
+use world_dispatcher_derive::Resource;
+
+#[derive(Resource)]
+enum Counter {
+    Stopped,
+    Counting(Cell<u32>),
+}
+
+fn main() {}